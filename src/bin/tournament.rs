@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use remote_colonies::local::tournament::{Tournament, TournamentConfig};
+
+const USAGE: &str =
+    "usage: tournament [config.json] [--matches N] [--format json|csv] [--output path]";
+
+struct Args {
+    config: Option<String>,
+    matches: Option<usize>,
+    csv: bool,
+    output: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        config: None,
+        matches: None,
+        csv: false,
+        output: None,
+    };
+    let mut values = env::args().skip(1);
+    while let Some(arg) = values.next() {
+        match arg.as_str() {
+            "--matches" => {
+                let value = values.next().ok_or("--matches needs a value")?;
+                let matches = value
+                    .parse()
+                    .map_err(|_| format!("invalid matches count {value}"))?;
+                args.matches = Some(matches);
+            }
+            "--format" => match values.next().as_deref() {
+                Some("json") => args.csv = false,
+                Some("csv") => args.csv = true,
+                _ => return Err("--format must be json or csv".to_string()),
+            },
+            "--output" => {
+                args.output = Some(values.next().ok_or("--output needs a path")?);
+            }
+            _ if arg.starts_with("--") || args.config.is_some() => {
+                return Err(format!("unexpected argument {arg}"));
+            }
+            _ => args.config = Some(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args().map_err(|e| format!("{e}\n{USAGE}"))?;
+    let mut config = match &args.config {
+        Some(path) => TournamentConfig::load(path).map_err(|e| format!("{e:?}"))?,
+        None => TournamentConfig::default(),
+    };
+    if let Some(matches) = args.matches {
+        config.matches = matches;
+    }
+
+    let report = Tournament::new(config).run();
+    let output = if args.csv {
+        report.to_csv()
+    } else {
+        report.to_json().map_err(|e| format!("{e:?}"))?
+    };
+    match &args.output {
+        Some(path) => fs::write(path, output).map_err(|e| e.to_string()),
+        None => {
+            println!("{output}");
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
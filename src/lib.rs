@@ -1,17 +1,24 @@
 //! # `remote_colonies` modules
 //!
 //! * `local` is the module that contains the game local logic
-//! * `renderer` the graphical visualization of the game state
+//! * `renderer` the graphical visualization of the game state, available with the `godot` feature
 pub mod local;
+#[cfg(feature = "godot")]
 pub mod renderer;
 
+#[cfg(feature = "godot")]
 use gdnative::init::*;
 
+#[cfg(feature = "godot")]
 use crate::renderer::godot2d::hud::HUD;
+#[cfg(feature = "godot")]
 use crate::renderer::godot2d::planet::Planet;
+#[cfg(feature = "godot")]
 use crate::renderer::godot2d::ship::Ship;
+#[cfg(feature = "godot")]
 use crate::renderer::godot2d::Main;
 
+#[cfg(feature = "godot")]
 fn init(handle: InitHandle) {
     handle.add_class::<Planet>();
     handle.add_class::<Ship>();
@@ -19,6 +26,9 @@ fn init(handle: InitHandle) {
     handle.add_class::<Main>();
 }
 
+#[cfg(feature = "godot")]
 godot_gdnative_init!();
+#[cfg(feature = "godot")]
 godot_nativescript_init!(init);
+#[cfg(feature = "godot")]
 godot_gdnative_terminate!();
@@ -1,33 +1,79 @@
-use crate::local::ai::mcts::transposition_table::ApproxTable;
+use crate::local::ai::mcts::transposition_table::{ApproxTable, TranspositionTable, VerifiedTable};
 use crate::local::ai::mcts::tree_policy::UCTPolicy;
-use crate::local::ai::mcts::{CycleBehaviour, Evaluator, Mcts, SearchHandle};
+use crate::local::ai::mcts::{CycleBehaviour, Evaluator, Mcts, SearchHandle, SearchNode};
 use crate::local::ai::AiState;
 use crate::local::model::*;
 use crate::local::player::*;
+use smallvec::SmallVec;
+
+/// The scores of the contenders in a searched state, by player id. Contenders
+/// without a score of their own, as the opponents of a paranoid search, play
+/// against the AI, which is always scored first.
+#[derive(Clone, Debug, Default)]
+pub struct AiEvaluation(SmallVec<[(usize, i64); 4]>);
+
+impl AiEvaluation {
+    pub fn push(&mut self, player_id: usize, score: i64) {
+        self.0.push((player_id, score));
+    }
+
+    pub fn get_score(&self, player_id: usize) -> i64 {
+        match self.0.iter().find(|(id, _)| *id == player_id) {
+            Some((_, score)) => *score,
+            None => self
+                .0
+                .first()
+                .map(|(_, score)| score.saturating_neg())
+                .unwrap_or(0),
+        }
+    }
+}
 
 pub struct MyEvaluator;
 
 impl Evaluator<MyMcts> for MyEvaluator {
-    type StateEvaluation = i64;
+    type StateEvaluation = AiEvaluation;
 
     fn evaluate_new_state(
         &self,
         state: &AiState,
         moves: &Vec<PlayerAction>,
         _: Option<SearchHandle<MyMcts>>,
-    ) -> (Vec<()>, i64) {
-        (vec![(); moves.len()], state.metrics.evaluate())
+    ) -> (Vec<()>, AiEvaluation) {
+        (vec![(); moves.len()], state.evaluate())
     }
-    fn interpret_evaluation_for_player(&self, evaln: &i64, _player: &ContenderProperties) -> i64 {
-        *evaln
+    fn interpret_evaluation_for_player(
+        &self,
+        evaln: &AiEvaluation,
+        player: &ContenderProperties,
+    ) -> i64 {
+        evaln.get_score(player.id)
     }
-    fn evaluate_existing_state(&self, _: &AiState, evaln: &i64, _: SearchHandle<MyMcts>) -> i64 {
-        *evaln
+    fn evaluate_existing_state(
+        &self,
+        _: &AiState,
+        evaln: &AiEvaluation,
+        _: SearchHandle<MyMcts>,
+    ) -> AiEvaluation {
+        evaln.clone()
     }
 }
 
-#[derive(Default)]
-pub struct MyMcts;
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+pub struct MyMcts {
+    virtual_loss: i64,
+}
+
+impl MyMcts {
+    pub const PARALLEL_VIRTUAL_LOSS: i64 = 100;
+
+    /// Searches sharing the tree across threads discourage them from descending the
+    /// same path at once; a sequential search keeps no virtual loss, so that it stays
+    /// reproducible from the policy seed alone.
+    pub fn with_virtual_loss(virtual_loss: i64) -> Self {
+        MyMcts { virtual_loss }
+    }
+}
 
 impl Mcts for MyMcts {
     type State = AiState;
@@ -35,9 +81,64 @@ impl Mcts for MyMcts {
     type NodeData = ();
     type ExtraThreadData = ();
     type TreePolicy = UCTPolicy;
-    type TranspositionTable = ApproxTable<Self>;
+    type TranspositionTable = AiTable;
+
+    fn virtual_loss(&self) -> i64 {
+        self.virtual_loss
+    }
 
     fn cycle_behaviour(&self) -> CycleBehaviour<Self> {
         CycleBehaviour::UseCurrentEvalWhenCycleDetected
     }
 }
+
+pub enum AiTable {
+    Approx(ApproxTable<MyMcts>),
+    Verified(VerifiedTable<MyMcts>),
+}
+
+impl AiTable {
+    pub fn new(verified: bool, capacity: usize) -> Self {
+        if verified {
+            AiTable::Verified(VerifiedTable::new(capacity))
+        } else {
+            AiTable::Approx(ApproxTable::new(capacity))
+        }
+    }
+}
+
+unsafe impl TranspositionTable<MyMcts> for AiTable {
+    fn insert<'a>(
+        &'a self,
+        key: &AiState,
+        value: &'a SearchNode<MyMcts>,
+        handle: SearchHandle<MyMcts>,
+    ) -> Option<&'a SearchNode<MyMcts>> {
+        match self {
+            AiTable::Approx(table) => table.insert(key, value, handle),
+            AiTable::Verified(table) => table.insert(key, value, handle),
+        }
+    }
+    fn lookup<'a>(
+        &'a self,
+        key: &AiState,
+        handle: SearchHandle<MyMcts>,
+    ) -> Option<&'a SearchNode<MyMcts>> {
+        match self {
+            AiTable::Approx(table) => table.lookup(key, handle),
+            AiTable::Verified(table) => table.lookup(key, handle),
+        }
+    }
+    fn retain(&mut self, is_kept: &dyn Fn(*const SearchNode<MyMcts>) -> bool) {
+        match self {
+            AiTable::Approx(table) => table.retain(is_kept),
+            AiTable::Verified(table) => table.retain(is_kept),
+        }
+    }
+    fn diagnose(&self) -> String {
+        match self {
+            AiTable::Approx(table) => TranspositionTable::<MyMcts>::diagnose(table),
+            AiTable::Verified(table) => TranspositionTable::<MyMcts>::diagnose(table),
+        }
+    }
+}
@@ -1,13 +1,11 @@
-#![allow(dead_code)]
-
 //! This is a library for Monte Carlo tree search.
 //!
 //! It is still under development and the documentation isn't good. However, the following example may be helpful:
 //!
 //! ```
-//! use mcts::*;
-//! use mcts::tree_policy::*;
-//! use mcts::transposition_table::*;
+//! use remote_colonies::local::ai::mcts::*;
+//! use remote_colonies::local::ai::mcts::tree_policy::*;
+//! use remote_colonies::local::ai::mcts::transposition_table::*;
 //!
 //! // A really simple game. There's one player and one number. In each move the player can
 //! // increase or decrease the number. The player's score is the number.
@@ -116,7 +114,8 @@ use tree_policy::*;
 
 use atomics::*;
 use std::sync::Arc;
-use std::thread::JoinHandle;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 pub trait Mcts: Sized + Sync {
     type State: GameState + Sync;
@@ -133,7 +132,7 @@ pub trait Mcts: Sized + Sync {
         1
     }
     fn node_limit(&self) -> usize {
-        std::usize::MAX
+        usize::MAX
     }
     fn select_child_after_search<'a>(&self, children: &'a [MoveInfo<Self>]) -> &'a MoveInfo<Self> {
         children.iter().max_by_key(|child| child.visits()).unwrap()
@@ -244,6 +243,11 @@ where
         self
     }
 
+    pub fn single_threaded_data(&mut self, tld: ThreadData<Spec>) -> &mut Self {
+        self.single_threaded_tld = Some(tld);
+        self
+    }
+
     pub fn playout(&mut self) {
         // Avoid overhead of thread creation
         if self.single_threaded_tld.is_none() {
@@ -262,7 +266,93 @@ where
             self.playout();
         }
     }
-    pub fn principal_variation_info(&self, num_moves: usize) -> Vec<MoveInfoHandle<Spec>> {
+    pub fn playout_n_parallel(&mut self, n: u32, num_threads: usize) {
+        if n == 0 {
+            return;
+        }
+        assert!(num_threads != 0);
+        let counter = AtomicIsize::new(n as isize);
+        let search_tree = &self.search_tree;
+        let print_on_playout_error = self.print_on_playout_error;
+        thread::scope(|scope| {
+            for _ in 0..num_threads {
+                scope.spawn(|| {
+                    let mut tld = Default::default();
+                    while counter.fetch_sub(1, Ordering::SeqCst) > 0 {
+                        if !search_tree.playout(&mut tld) {
+                            if print_on_playout_error {
+                                eprintln!(
+                                    "Node limit of {} reached. Halting search.",
+                                    search_tree.spec().node_limit()
+                                );
+                            }
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    }
+    pub fn playout_parallel_async(&mut self, num_threads: usize) -> AsyncSearch<'_, Spec>
+    where
+        Spec: 'static,
+    {
+        assert!(num_threads != 0);
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let threads = spawn_search_threads(
+            &self.search_tree,
+            self.print_on_playout_error,
+            &stop_signal,
+            num_threads,
+        );
+        AsyncSearch {
+            manager: self,
+            stop_signal,
+            threads,
+        }
+    }
+    pub fn into_playout_parallel_async(self, num_threads: usize) -> AsyncSearchOwned<Spec>
+    where
+        Spec: 'static,
+    {
+        assert!(num_threads != 0);
+        // The tree is boxed so that its address stays valid for the search threads
+        // when the returned value is moved around.
+        let manager = Box::new(self);
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let threads = spawn_search_threads(
+            &manager.search_tree,
+            manager.print_on_playout_error,
+            &stop_signal,
+            num_threads,
+        );
+        AsyncSearchOwned {
+            manager: Some(manager),
+            stop_signal,
+            threads,
+        }
+    }
+    pub fn playout_parallel_for(&mut self, duration: Duration, num_threads: usize)
+    where
+        Spec: 'static,
+    {
+        let search = self.playout_parallel_async(num_threads);
+        thread::sleep(duration);
+        search.halt();
+    }
+    pub fn advance(&mut self, mov: &Move<Spec>) -> Result<(), AdvanceError>
+    where
+        Move<Spec>: PartialEq,
+    {
+        self.search_tree.advance(mov)
+    }
+    pub fn set_root_state(&mut self, state: Spec::State) -> Result<(), AdvanceError>
+    where
+        Move<Spec>: PartialEq,
+    {
+        self.search_tree.set_root_state(state)
+    }
+    pub fn principal_variation_info(&self, num_moves: usize) -> Vec<MoveInfoHandle<'_, Spec>> {
         self.search_tree.principal_variation(num_moves)
     }
     pub fn principal_variation(&self, num_moves: usize) -> Vec<Move<Spec>> {
@@ -286,7 +376,7 @@ where
         &self.search_tree
     }
     pub fn best_move(&self) -> Option<Move<Spec>> {
-        self.principal_variation(1).get(0).cloned()
+        self.principal_variation(1).first().cloned()
     }
     pub fn reset(self) -> Self {
         Self {
@@ -309,6 +399,44 @@ fn thousands_separate(x: usize) -> String {
     String::from_utf8(result).unwrap()
 }
 
+struct SearchTreePtr<Spec: Mcts>(*const SearchTree<Spec>);
+
+// The search threads only call `SearchTree::playout`, which is safe to share, and
+// are always joined before the tree they point to is dropped or mutated.
+unsafe impl<Spec: Mcts> Send for SearchTreePtr<Spec> {}
+
+fn spawn_search_threads<Spec: Mcts + 'static>(
+    search_tree: &SearchTree<Spec>,
+    print_on_playout_error: bool,
+    stop_signal: &Arc<AtomicBool>,
+    num_threads: usize,
+) -> Vec<JoinHandle<()>>
+where
+    ThreadData<Spec>: Default,
+{
+    (0..num_threads)
+        .map(|_| {
+            let stop_signal = stop_signal.clone();
+            let search_tree = SearchTreePtr(search_tree as *const SearchTree<Spec>);
+            thread::spawn(move || {
+                let search_tree = unsafe { &*search_tree.0 };
+                let mut tld = Default::default();
+                while !stop_signal.load(Ordering::SeqCst) {
+                    if !search_tree.playout(&mut tld) {
+                        if print_on_playout_error {
+                            eprintln!(
+                                "Node limit of {} reached. Halting search.",
+                                search_tree.spec().node_limit()
+                            );
+                        }
+                        break;
+                    }
+                }
+            })
+        })
+        .collect()
+}
+
 #[must_use]
 pub struct AsyncSearch<'a, Spec: 'a + Mcts> {
     manager: &'a mut MctsManager<Spec>,
@@ -318,6 +446,9 @@ pub struct AsyncSearch<'a, Spec: 'a + Mcts> {
 
 impl<'a, Spec: Mcts> AsyncSearch<'a, Spec> {
     pub fn halt(self) {}
+    pub fn manager(&self) -> &MctsManager<Spec> {
+        self.manager
+    }
     pub fn num_threads(&self) -> usize {
         self.threads.len()
     }
@@ -381,3 +512,151 @@ pub enum CycleBehaviour<Spec: Mcts> {
     PanicWhenCycleDetected,
     UseThisEvalWhenCycleDetected(StateEvaluation<Spec>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct CountingGame(i64);
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Move {
+        Add,
+        Sub,
+    }
+
+    impl GameState for CountingGame {
+        type Move = Move;
+        type Player = ();
+        type MoveList = Vec<Move>;
+
+        fn current_player(&self) -> Self::Player {}
+        fn available_moves(&self) -> Vec<Move> {
+            if self.0 == 100 {
+                vec![]
+            } else {
+                vec![Move::Add, Move::Sub]
+            }
+        }
+        fn make_move(&mut self, mov: &Self::Move) {
+            match *mov {
+                Move::Add => self.0 += 1,
+                Move::Sub => self.0 -= 1,
+            }
+        }
+    }
+
+    impl TranspositionHash for CountingGame {
+        fn hash(&self) -> u64 {
+            self.0 as u64
+        }
+    }
+
+    struct CountingEvaluator;
+
+    impl Evaluator<CountingMcts> for CountingEvaluator {
+        type StateEvaluation = i64;
+
+        fn evaluate_new_state(
+            &self,
+            state: &CountingGame,
+            moves: &Vec<Move>,
+            _: Option<SearchHandle<CountingMcts>>,
+        ) -> (Vec<()>, i64) {
+            (vec![(); moves.len()], state.0)
+        }
+        fn interpret_evaluation_for_player(&self, evaln: &i64, _: &()) -> i64 {
+            *evaln
+        }
+        fn evaluate_existing_state(
+            &self,
+            _: &CountingGame,
+            evaln: &i64,
+            _: SearchHandle<CountingMcts>,
+        ) -> i64 {
+            *evaln
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingMcts;
+
+    impl Mcts for CountingMcts {
+        type State = CountingGame;
+        type Eval = CountingEvaluator;
+        type NodeData = ();
+        type ExtraThreadData = ();
+        type TreePolicy = UCTPolicy;
+        type TranspositionTable = ApproxTable<Self>;
+
+        fn cycle_behaviour(&self) -> CycleBehaviour<Self> {
+            CycleBehaviour::UseCurrentEvalWhenCycleDetected
+        }
+    }
+
+    fn searched_manager(playouts: u64) -> MctsManager<CountingMcts> {
+        let mut manager = MctsManager::new(
+            CountingGame(0),
+            CountingMcts,
+            CountingEvaluator,
+            UCTPolicy::new(0.5),
+            ApproxTable::new(1024),
+        );
+        manager.playout_n(playouts);
+        manager
+    }
+
+    /// Visits of the moves of the node reached by `path` from `node`.
+    fn visits_after(node: NodeHandle<'_, CountingMcts>, path: &[Move]) -> Vec<(Move, u64)> {
+        match path.split_first() {
+            None => node
+                .moves()
+                .map(|move_info| (move_info.get_move().clone(), move_info.visits()))
+                .collect(),
+            Some((mov, path)) => {
+                let move_info = node
+                    .moves()
+                    .find(|move_info| move_info.get_move() == mov)
+                    .unwrap();
+                visits_after(move_info.child().unwrap(), path)
+            }
+        }
+    }
+
+    #[test]
+    fn advance_keeps_the_visits_of_the_chosen_child() {
+        let mut manager = searched_manager(1000);
+        let nodes_count = manager.tree().num_nodes();
+        let child_visits = visits_after(manager.tree().root_node(), &[Move::Add]);
+        assert!(child_visits.iter().any(|(_, visits)| *visits > 0));
+
+        manager.advance(&Move::Add).unwrap();
+
+        assert_eq!(manager.tree().root_state(), &CountingGame(1));
+        assert_eq!(visits_after(manager.tree().root_node(), &[]), child_visits);
+        assert!(manager.tree().num_nodes() < nodes_count);
+        manager.playout_n(100);
+        assert_eq!(manager.best_move(), Some(Move::Add));
+    }
+
+    #[test]
+    fn advance_needs_an_expanded_child() {
+        let mut manager = searched_manager(0);
+        assert!(matches!(
+            manager.advance(&Move::Add),
+            Err(AdvanceError::ChildNotExpanded)
+        ));
+    }
+
+    #[test]
+    fn set_root_state_reuses_a_transposition() {
+        let mut manager = searched_manager(1000);
+        let node_visits = visits_after(manager.tree().root_node(), &[Move::Add, Move::Add]);
+
+        manager.set_root_state(CountingGame(2)).unwrap();
+
+        assert_eq!(manager.tree().root_state(), &CountingGame(2));
+        assert_eq!(visits_after(manager.tree().root_node(), &[]), node_visits);
+    }
+}
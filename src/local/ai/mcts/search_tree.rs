@@ -1,10 +1,9 @@
-#![cfg_attr(feature = "nightly", feature(integer_atomics))]
-
 use std;
 
 use super::*;
 use atomics::*;
 use smallvec::SmallVec;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::ptr::null_mut;
@@ -29,6 +28,14 @@ pub struct SearchTree<Spec: Mcts> {
     expansion_contention_events: AtomicUsize,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AdvanceError {
+    MoveNotFound,
+    ChildNotExpanded,
+    ChildNotOwned,
+    MovesMismatch,
+}
+
 struct NodeStats {
     visits: AtomicUsize,
     sum_evaluations: AtomicI64,
@@ -87,7 +94,7 @@ impl<Spec: Mcts> MoveInfo<Spec> {
         self.stats.sum_evaluations.load(Ordering::Relaxed) as i64
     }
 
-    pub fn child(&self) -> Option<NodeHandle<Spec>> {
+    pub fn child(&self) -> Option<NodeHandle<'_, Spec>> {
         let ptr = self.child.load(Ordering::Relaxed);
         if ptr.is_null() {
             None
@@ -174,7 +181,7 @@ fn create_node<Spec: Mcts>(
     policy.validate_evaluations(&move_eval);
     let moves = moves
         .into_iter()
-        .zip(move_eval.into_iter())
+        .zip(move_eval)
         .map(|(m, e)| MoveInfo::new(m, e))
         .collect();
     SearchNode::new(moves, state_eval)
@@ -218,6 +225,135 @@ impl<Spec: Mcts> SearchTree<Spec> {
         )
     }
 
+    /// Makes the child reached by `mov` the new root, keeping its subtree and dropping
+    /// the rest of the tree along with the transpositions into it. A child reached
+    /// through a transposition is taken over from the move owning it.
+    pub fn advance(&mut self, mov: &Move<Spec>) -> Result<(), AdvanceError>
+    where
+        Move<Spec>: PartialEq,
+    {
+        let choice = self
+            .root_node
+            .moves
+            .iter()
+            .find(|move_info| move_info.mov == *mov)
+            .ok_or(AdvanceError::MoveNotFound)?;
+        let child = choice.child.load(Ordering::SeqCst);
+        if child.is_null() {
+            return Err(AdvanceError::ChildNotExpanded);
+        }
+        let owner = if choice.owned.load(Ordering::SeqCst) {
+            choice
+        } else {
+            self.find_owner(child).ok_or(AdvanceError::ChildNotOwned)?
+        };
+        owner.owned.store(false, Ordering::SeqCst);
+        self.root_node = unsafe { *Box::from_raw(child) };
+        self.root_state.make_move(mov);
+        self.prune();
+        Ok(())
+    }
+
+    /// Replaces the root state, as when the game moved on since the last search.
+    /// A node of the tree with the same state hash becomes the new root, otherwise
+    /// the root keeps the subtrees of the moves still available and gets new
+    /// children for the others. Fails when none of the moves is available anymore.
+    pub fn set_root_state(&mut self, state: Spec::State) -> Result<(), AdvanceError>
+    where
+        Move<Spec>: PartialEq,
+        ThreadData<Spec>: Default,
+    {
+        let moves = state.available_moves();
+        let (move_eval, state_eval) = self.eval.evaluate_new_state(&state, &moves, None);
+        self.tree_policy.validate_evaluations(&move_eval);
+        let moves: Vec<Move<Spec>> = moves.into_iter().collect();
+
+        let mut tld = ThreadData::default();
+        let transposition = self
+            .table
+            .lookup(&state, self.make_handle(&self.root_node, &mut tld))
+            .map(|node| node as *const SearchNode<Spec>);
+        let owner = transposition.and_then(|node| self.find_owner(node));
+        let new_root = match owner {
+            Some(owner) => unsafe { &*owner.child.load(Ordering::SeqCst) },
+            None => &self.root_node,
+        };
+        if !moves
+            .iter()
+            .any(|mov| new_root.moves.iter().any(|info| info.mov == *mov))
+        {
+            return Err(AdvanceError::MovesMismatch);
+        }
+        if let Some(owner) = owner {
+            let child = owner.child.load(Ordering::SeqCst);
+            owner.owned.store(false, Ordering::SeqCst);
+            self.root_node = unsafe { *Box::from_raw(child) };
+        }
+        let mut previous_moves = std::mem::take(&mut self.root_node.moves);
+        self.root_node.moves = moves
+            .into_iter()
+            .zip(move_eval)
+            .map(
+                |(mov, eval)| match previous_moves.iter().position(|info| info.mov == mov) {
+                    Some(index) => previous_moves.swap_remove(index),
+                    None => MoveInfo::new(mov, eval),
+                },
+            )
+            .collect();
+        drop(previous_moves);
+        self.root_node.evaln = state_eval;
+        self.root_state = state;
+        self.prune();
+        Ok(())
+    }
+
+    /// Finds the move owning `node` among the nodes reachable from the root.
+    fn find_owner(&self, node: *const SearchNode<Spec>) -> Option<&MoveInfo<Spec>> {
+        let mut nodes = vec![&self.root_node];
+        while let Some(current) = nodes.pop() {
+            for move_info in &current.moves {
+                let child = move_info.child.load(Ordering::SeqCst);
+                if child.is_null() || !move_info.owned.load(Ordering::SeqCst) {
+                    continue;
+                }
+                if std::ptr::eq(child, node) {
+                    return Some(move_info);
+                }
+                nodes.push(unsafe { &*child });
+            }
+        }
+        None
+    }
+
+    /// Forgets everything that does not hang from the root anymore: the pointers
+    /// of transpositions into dropped nodes, their table entries and the orphans.
+    fn prune(&mut self) {
+        self.orphaned.get_mut().unwrap().clear();
+
+        let mut kept = vec![&self.root_node];
+        let mut index = 0;
+        while index < kept.len() {
+            for move_info in &kept[index].moves {
+                let child = move_info.child.load(Ordering::SeqCst);
+                if !child.is_null() && move_info.owned.load(Ordering::SeqCst) {
+                    kept.push(unsafe { &*child });
+                }
+            }
+            index += 1;
+        }
+        let kept_ptrs: HashSet<*const SearchNode<Spec>> =
+            kept.iter().map(|node| *node as *const _).collect();
+        for move_info in kept.iter().flat_map(|node| node.moves.iter()) {
+            let child = move_info.child.load(Ordering::SeqCst);
+            if !child.is_null() && !kept_ptrs.contains(&(child as *const _)) {
+                move_info.child.store(null_mut(), Ordering::SeqCst);
+            }
+        }
+        let kept_count = kept.len();
+        self.table.retain(&|node| kept_ptrs.contains(&node));
+        *self.num_nodes.get_mut() = kept_count;
+    }
+
     pub fn spec(&self) -> &Spec {
         &self.manager
     }
@@ -416,13 +552,13 @@ impl<Spec: Mcts> SearchTree<Spec> {
     pub fn root_state(&self) -> &Spec::State {
         &self.root_state
     }
-    pub fn root_node(&self) -> NodeHandle<Spec> {
+    pub fn root_node(&self) -> NodeHandle<'_, Spec> {
         NodeHandle {
             node: &self.root_node,
         }
     }
 
-    pub fn principal_variation(&self, num_moves: usize) -> Vec<MoveInfoHandle<Spec>> {
+    pub fn principal_variation(&self, num_moves: usize) -> Vec<MoveInfoHandle<'_, Spec>> {
         let mut result = Vec::new();
         let mut crnt = &self.root_node;
         while !crnt.moves.is_empty() && result.len() < num_moves {
@@ -465,6 +601,7 @@ impl<Spec: Mcts> SearchTree<Spec> {
             "{} orphaned nodes\n",
             self.orphaned.lock().unwrap().len()
         ));
+        s.push_str(&self.table.diagnose());
         s
     }
 }
@@ -506,7 +643,7 @@ impl<'a, Spec: Mcts> NodeHandle<'a, Spec> {
     pub fn data(&self) -> &'a Spec::NodeData {
         &self.node.data
     }
-    pub fn moves(&self) -> Moves<Spec> {
+    pub fn moves(&self) -> Moves<'_, Spec> {
         Moves {
             iter: self.node.moves.iter(),
         }
@@ -514,6 +651,9 @@ impl<'a, Spec: Mcts> NodeHandle<'a, Spec> {
     pub fn into_raw(self) -> *const () {
         self.node as *const _ as *const ()
     }
+    /// # Safety
+    ///
+    /// `ptr` must come from `into_raw` on a node of a tree that is still alive.
     pub unsafe fn from_raw(ptr: *const ()) -> Self {
         NodeHandle {
             node: &*(ptr as *const SearchNode<Spec>),
@@ -1,6 +1,8 @@
 use super::*;
 use atomics::*;
 use search_tree::*;
+use std::collections::HashMap;
+use std::sync::RwLock;
 
 /// # Safety
 /// unsafe
@@ -40,6 +42,15 @@ pub unsafe trait TranspositionTable<Spec: Mcts>: Sync + Sized {
         key: &Spec::State,
         handle: SearchHandle<Spec>,
     ) -> Option<&'a SearchNode<Spec>>;
+
+    /// Forgets the keys whose node is not kept, as it may be dropped afterwards.
+    /// The pointers of the forgotten nodes must not be dereferenced.
+    fn retain(&mut self, is_kept: &dyn Fn(*const SearchNode<Spec>) -> bool);
+
+    /// Describes the table statistics, if any, for `SearchTree::diagnose`.
+    fn diagnose(&self) -> String {
+        String::new()
+    }
 }
 
 unsafe impl<Spec: Mcts<TranspositionTable = Self>> TranspositionTable<Spec> for () {
@@ -59,12 +70,22 @@ unsafe impl<Spec: Mcts<TranspositionTable = Self>> TranspositionTable<Spec> for
     ) -> Option<&'a SearchNode<Spec>> {
         None
     }
+
+    fn retain(&mut self, _: &dyn Fn(*const SearchNode<Spec>) -> bool) {}
 }
 
 pub trait TranspositionHash {
     fn hash(&self) -> u64;
 }
 
+/// The exact key of a state, which the verified table compares on top of the hash
+/// to tell transpositions apart from collisions.
+pub trait TranspositionKey: TranspositionHash {
+    type Key: Eq + Send + Sync;
+
+    fn key(&self) -> Self::Key;
+}
+
 pub struct ApproxQuadraticProbingHashTable<K: TranspositionHash, V> {
     arr: Box<[Entry16<K, V>]>,
     capacity: usize,
@@ -151,19 +172,10 @@ fn convert<'a, V>(ptr: *const V) -> Option<&'a V> {
 
 const PROBE_LIMIT: usize = 16;
 
-unsafe impl<Spec> TranspositionTable<Spec> for ApproxTable<Spec>
-where
-    Spec::State: TranspositionHash,
-    Spec: Mcts,
-{
-    fn insert<'a>(
-        &'a self,
-        key: &Spec::State,
-        value: &'a SearchNode<Spec>,
-        handle: SearchHandle<Spec>,
-    ) -> Option<&'a SearchNode<Spec>> {
+impl<K: TranspositionHash, V> ApproxQuadraticProbingHashTable<K, V> {
+    pub fn insert_value<'a>(&'a self, key: &K, value: &'a V) -> Option<&'a V> {
         if self.size.load(Ordering::Relaxed) * 3 > self.capacity * 2 {
-            return self.lookup(key, handle);
+            return self.lookup_value(key);
         }
         let my_hash = key.hash();
         if my_hash == 0 {
@@ -200,11 +212,7 @@ where
         }
         None
     }
-    fn lookup<'a>(
-        &'a self,
-        key: &Spec::State,
-        _: SearchHandle<Spec>,
-    ) -> Option<&'a SearchNode<Spec>> {
+    pub fn lookup_value<'a>(&'a self, key: &K) -> Option<&'a V> {
         let my_hash = key.hash();
         let mut posn = my_hash as usize & self.mask;
         for inc in 1..(PROBE_LIMIT + 1) {
@@ -221,4 +229,236 @@ where
         }
         None
     }
+    pub fn retain_values(&mut self, is_kept: &dyn Fn(*const V) -> bool) {
+        let kept: Vec<(u64, *mut V)> = self
+            .arr
+            .iter()
+            .map(|entry| {
+                (
+                    entry.k.load(Ordering::Relaxed) as u64,
+                    entry.v.load(Ordering::Relaxed),
+                )
+            })
+            .filter(|(hash, value)| *hash != 0 && !value.is_null() && is_kept(*value))
+            .collect();
+        self.arr
+            .iter_mut()
+            .for_each(|entry| *entry = Entry16::default());
+        *self.size.get_mut() = 0;
+        // Probing chains are rebuilt rather than emptied in place, since a lookup
+        // stops at the first empty entry.
+        for (hash, value) in kept {
+            let mut posn = hash as usize & self.mask;
+            for inc in 1..(PROBE_LIMIT + 1) {
+                let entry = &mut self.arr[posn];
+                if *entry.k.get_mut() == 0 {
+                    *entry.k.get_mut() = hash as FakeU64;
+                    *entry.v.get_mut() = value;
+                    *self.size.get_mut() += 1;
+                    break;
+                }
+                posn += inc;
+                posn &= self.mask;
+            }
+        }
+    }
+}
+
+unsafe impl<Spec> TranspositionTable<Spec> for ApproxTable<Spec>
+where
+    Spec::State: TranspositionHash,
+    Spec: Mcts,
+{
+    fn insert<'a>(
+        &'a self,
+        key: &Spec::State,
+        value: &'a SearchNode<Spec>,
+        _: SearchHandle<Spec>,
+    ) -> Option<&'a SearchNode<Spec>> {
+        self.insert_value(key, value)
+    }
+    fn lookup<'a>(
+        &'a self,
+        key: &Spec::State,
+        _: SearchHandle<Spec>,
+    ) -> Option<&'a SearchNode<Spec>> {
+        self.lookup_value(key)
+    }
+    fn retain(&mut self, is_kept: &dyn Fn(*const SearchNode<Spec>) -> bool) {
+        self.retain_values(is_kept)
+    }
+}
+
+/// A table that stores the exact key of each state along with its node, so that
+/// states whose hashes collide never share their statistics. It is slower than the
+/// approximate table, as it locks a map and computes the key on every access.
+pub struct VerifiedHashTable<K: TranspositionKey, V> {
+    entries: RwLock<HashMap<u64, (K::Key, *const V)>>,
+    capacity: usize,
+    hits: AtomicUsize,
+    collisions: AtomicUsize,
+}
+
+impl<K: TranspositionKey, V> VerifiedHashTable<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::with_capacity(capacity)),
+            capacity,
+            hits: AtomicUsize::default(),
+            collisions: AtomicUsize::default(),
+        }
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn collisions(&self) -> usize {
+        self.collisions.load(Ordering::Relaxed)
+    }
+
+    /// Inserts the value unless the hash is already known, in which case the value of
+    /// the same key is returned, and `None` on a collision. The map is only locked for
+    /// writing to insert, so that the searching threads mostly share the read lock.
+    pub fn insert_value<'a>(&'a self, key: &K, value: &'a V) -> Option<&'a V> {
+        let my_hash = key.hash();
+        if let Some(entry) = self.entries.read().unwrap().get(&my_hash) {
+            return self.verify(entry, &key.key());
+        }
+        let mut entries = self.entries.write().unwrap();
+        // Another thread may have inserted the hash since the read lock was released.
+        if let Some(entry) = entries.get(&my_hash) {
+            return self.verify(entry, &key.key());
+        }
+        if entries.len() < self.capacity {
+            entries.insert(my_hash, (key.key(), value as *const _));
+        }
+        None
+    }
+
+    pub fn lookup_value<'a>(&'a self, key: &K) -> Option<&'a V> {
+        let entries = self.entries.read().unwrap();
+        let entry = entries.get(&key.hash())?;
+        self.verify(entry, &key.key())
+    }
+
+    fn verify<'a>(&'a self, entry: &(K::Key, *const V), key: &K::Key) -> Option<&'a V> {
+        let (key_here, value_here) = entry;
+        if key_here == key {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            convert(*value_here)
+        } else {
+            self.collisions.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+unsafe impl<K: TranspositionKey, V> Sync for VerifiedHashTable<K, V> {}
+unsafe impl<K: TranspositionKey, V> Send for VerifiedHashTable<K, V> {}
+
+pub type VerifiedTable<Spec> = VerifiedHashTable<<Spec as Mcts>::State, SearchNode<Spec>>;
+
+unsafe impl<Spec> TranspositionTable<Spec> for VerifiedTable<Spec>
+where
+    Spec::State: TranspositionKey,
+    Spec: Mcts,
+{
+    fn insert<'a>(
+        &'a self,
+        key: &Spec::State,
+        value: &'a SearchNode<Spec>,
+        _: SearchHandle<Spec>,
+    ) -> Option<&'a SearchNode<Spec>> {
+        self.insert_value(key, value)
+    }
+    fn lookup<'a>(
+        &'a self,
+        key: &Spec::State,
+        _: SearchHandle<Spec>,
+    ) -> Option<&'a SearchNode<Spec>> {
+        self.lookup_value(key)
+    }
+    fn retain(&mut self, is_kept: &dyn Fn(*const SearchNode<Spec>) -> bool) {
+        self.entries
+            .get_mut()
+            .unwrap()
+            .retain(|_, (_, value)| is_kept(*value));
+    }
+    fn diagnose(&self) -> String {
+        format!(
+            "{} verified transposition table hits\n{} hash collisions\n",
+            self.hits(),
+            self.collisions()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestState {
+        hash: u64,
+        key: u32,
+    }
+
+    impl TranspositionHash for TestState {
+        fn hash(&self) -> u64 {
+            self.hash
+        }
+    }
+
+    impl TranspositionKey for TestState {
+        type Key = u32;
+
+        fn key(&self) -> u32 {
+            self.key
+        }
+    }
+
+    #[test]
+    fn verified_table_detects_collisions() {
+        let table: VerifiedHashTable<TestState, u32> = VerifiedHashTable::new(16);
+        let state = TestState { hash: 7, key: 1 };
+        let colliding = TestState { hash: 7, key: 2 };
+        let (value, other_value) = (10, 20);
+
+        assert_eq!(table.insert_value(&state, &value), None);
+        assert_eq!(table.lookup_value(&state), Some(&value));
+        assert_eq!((table.hits(), table.collisions()), (1, 0));
+
+        assert_eq!(table.lookup_value(&colliding), None);
+        assert_eq!(table.collisions(), 1);
+        assert_eq!(table.insert_value(&colliding, &other_value), None);
+        assert_eq!(table.collisions(), 2);
+
+        assert_eq!(table.insert_value(&state, &other_value), Some(&value));
+        assert_eq!((table.hits(), table.collisions()), (2, 2));
+    }
+
+    #[test]
+    fn approx_table_keeps_probing_chains_on_retain() {
+        let mut table: ApproxQuadraticProbingHashTable<TestState, u32> =
+            ApproxQuadraticProbingHashTable::new(16);
+        // The three hashes share their first slot, so each one probes past the others.
+        let states: Vec<TestState> = [1, 17, 33]
+            .iter()
+            .map(|hash| TestState {
+                hash: *hash,
+                key: 0,
+            })
+            .collect();
+        let values = [10, 20, 30];
+        for (state, value) in states.iter().zip(values.iter()) {
+            assert_eq!(table.insert_value(state, value), None);
+        }
+
+        let dropped: *const u32 = &values[0];
+        table.retain_values(&|value| !std::ptr::eq(value, dropped));
+
+        assert_eq!(table.lookup_value(&states[0]), None);
+        assert_eq!(table.lookup_value(&states[1]), Some(&values[1]));
+        assert_eq!(table.lookup_value(&states[2]), Some(&values[2]));
+    }
 }
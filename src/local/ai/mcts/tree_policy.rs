@@ -4,7 +4,6 @@ use rand::{Rng, SeedableRng};
 
 use super::*;
 use search_tree::*;
-use std;
 
 pub trait TreePolicy<Spec: Mcts<TreePolicy = Self>>: Sync + Sized {
     type MoveEvaluation: Sync + Send;
@@ -102,7 +101,7 @@ impl<Spec: Mcts<TreePolicy = Self>> TreePolicy<Spec> for UCTPolicy {
                 let child_visits = mov.visits();
                 // http://mcts.ai/pubs/mcts-survey-master.pdf
                 if child_visits == 0 {
-                    std::f64::INFINITY
+                    f64::INFINITY
                 } else {
                     let explore_term = 2.0 * (ln_adjusted_total / child_visits as f64).sqrt();
                     let mean_action_value = sum_rewards as f64 / child_visits as f64;
@@ -174,6 +173,12 @@ impl PolicyRng {
         Self { rng }
     }
 
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
     pub fn select_by_key<T, Iter, KeyFn>(&mut self, elts: Iter, mut key_fn: KeyFn) -> Option<T>
     where
         Iter: Iterator<Item = T>,
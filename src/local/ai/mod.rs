@@ -1,20 +1,44 @@
 mod evaluator;
-mod mcts;
+pub mod mcts;
+pub mod personality;
+pub mod pool;
+mod rollout;
+pub mod scheduler;
 
-use crate::local::Difficulty;
+use crate::local::difficulty::{Difficulty, DifficultyProfile};
 use mcts::transposition_table::*;
-use mcts::tree_policy::UCTPolicy;
-use mcts::{GameState, MctsManager};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use mcts::tree_policy::{PolicyRng, UCTPolicy};
+use mcts::{GameState, MctsManager, ThreadData};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
 
 use self::evaluator::*;
+use self::personality::{AiPersonality, PersonalityProfile};
+use self::rollout::Rollout;
+use super::fleet::Fleet;
 use super::model::*;
 use super::planet::PlanetBusiness;
 use super::player::*;
+use super::rng::StreamRng;
+use super::ruleset::RuleSet;
 
 type ContenderVessels = (ContenderProperties, Vec<VesselProperties>);
 
+/// How the search accounts for the opponents: `single` only chains the moves of the
+/// AI, while the other modes alternate the contenders and let the opponents reply,
+/// either each one maximizing its own evaluation (`max_n`) or all of them minimizing
+/// the evaluation of the AI (`paranoid`).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Single,
+    MaxN,
+    Paranoid,
+}
+
 #[derive(Clone, Debug)]
 struct Measure {
     planet_props: CelestialProperties,
@@ -22,11 +46,13 @@ struct Measure {
     extracted: f32,
     ships_count: usize,
     allied_ships_count: usize,
+    incoming_allied_ships_count: usize,
     distances: Vec<f32>,
     distance: f32,
+    visible: bool,
 }
 
-#[derive(Clone, Debug, Hash)]
+#[derive(Clone, Debug)]
 struct Metrics {
     enemy_extracted: i64,
     allied_extracted: i64,
@@ -35,8 +61,66 @@ struct Metrics {
     planets_ratio: i64,
 }
 
+fn count_ships(ships_by_player: &[ContenderVessels], contender_id: usize) -> usize {
+    ships_by_player
+        .iter()
+        .filter(|(player, _)| player.id == contender_id)
+        .map(|(_, ships)| ships.len())
+        .sum()
+}
+
 impl Metrics {
-    pub fn evaluate(&self) -> i64 {
+    fn measure(player_id: usize, measures: &[Measure]) -> Self {
+        Self::measure_with(player_id, measures, |m| m.allied_ships_count)
+    }
+
+    /// Measures an opponent, whose ships are counted on the planets since the
+    /// allied counts of the measures are those of the AI.
+    fn measure_opponent(opponent_id: usize, measures: &[Measure]) -> Self {
+        Self::measure_with(opponent_id, measures, |m| {
+            count_ships(&m.ships_by_player, opponent_id)
+        })
+    }
+
+    fn measure_with<F>(player_id: usize, measures: &[Measure], allied_ships_count: F) -> Self
+    where
+        F: Fn(&Measure) -> usize,
+    {
+        let allied_measures: Vec<&Measure> = measures
+            .iter()
+            .filter(|m| m.planet_props.contender_id == player_id)
+            .collect();
+        let enemy_measures: Vec<&Measure> = measures
+            .iter()
+            .filter(|m| m.planet_props.contender_id != player_id)
+            .collect();
+
+        let allied_extracted = allied_measures
+            .iter()
+            .map(|m| m.extracted)
+            .fold(0.0, |acc, r| acc + r.floor());
+        let enemy_extracted = enemy_measures
+            .iter()
+            .map(|m| (m.planet_props.extracted + m.planet_props.resources) / m.distance)
+            .fold(0.0, |acc, r| acc + r.floor());
+        let allied_ships_count = measures
+            .iter()
+            .map(|m| allied_ships_count(m) as f32)
+            .fold(0.0, |acc, s| acc + s);
+        let total_ships_count = measures
+            .iter()
+            .map(|m| m.ships_count)
+            .fold(0.0, |acc, s| acc + s as f32);
+        Metrics {
+            allied_extracted: allied_extracted.floor() as i64,
+            enemy_extracted: enemy_extracted.floor() as i64,
+            ships_count: allied_ships_count.floor() as i64,
+            ships_count_ratio: (100.0 * allied_ships_count / total_ships_count).floor() as i64,
+            planets_ratio: (100.0 * allied_measures.len() as f32 / measures.len() as f32) as i64,
+        }
+    }
+
+    pub fn evaluate(&self, profile: &PersonalityProfile) -> i64 {
         let allied_extracted = self.allied_extracted as f32;
         let enemy_extracted = self.enemy_extracted as f32;
         let ships_count = self.ships_count as f32;
@@ -51,25 +135,78 @@ impl Metrics {
         let strat_consume_own_and_conquer_most_extracted =
             planets_ratio * ships_count_ratio * 10.0f32.powf(enemy_extracted)
                 / 1.0001f32.powf(allied_extracted);
-        let benefit = strat_more_ships_when_disadvantage
-            * strat_more_conquer_planet_when_advantage
-            * strat_consume_own_and_conquer_most_extracted;
+        let benefit = strat_more_ships_when_disadvantage.powf(profile.disadvantage_weight)
+            * strat_more_conquer_planet_when_advantage.powf(profile.advantage_weight)
+            * strat_consume_own_and_conquer_most_extracted.powf(profile.extraction_weight);
         (100.0 * benefit).round() as i64
     }
 }
 
+/// The search tree is not saved, so a restored AI starts its next search from scratch.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct AiSnapshot {
+    pub player: ContenderProperties,
+    pub difficulty: Difficulty,
+    pub personality: AiPersonality,
+    pub seed: u64,
+    pub word_pos: u128,
+    pub last_decision_time: f64,
+    pub next_decision_time: f64,
+}
+
+/// The search trees kept between decisions, one for each sample of the fog of war
+/// or a single one without it. Clones start without any, as the states explored by
+/// the search are clones of the AI too.
+#[derive(Default)]
+struct SearchCache(Vec<MctsManager<MyMcts>>);
+
+impl Clone for SearchCache {
+    fn clone(&self) -> Self {
+        SearchCache(vec![])
+    }
+}
+
+impl fmt::Debug for SearchCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes: Vec<usize> = self.0.iter().map(|mcts| mcts.tree().num_nodes()).collect();
+        write!(f, "SearchCache({nodes:?} nodes)")
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AiState {
     player: ContenderProperties,
+    current: ContenderProperties,
+    opponents: Vec<ContenderProperties>,
     metrics: Metrics,
     measures: Vec<Measure>,
     difficulty: Difficulty,
+    difficulty_profile: DifficultyProfile,
+    personality: AiPersonality,
+    personality_profile: PersonalityProfile,
+    rules: RuleSet,
+    seed: u64,
+    rng: StreamRng,
+    last_decision_time: f64,
+    next_decision_time: f64,
+    observed_replies: Vec<(usize, usize, usize)>,
+    search: SearchCache,
 }
 
 impl AiState {
-    pub fn new(player: ContenderProperties, difficulty: Difficulty) -> Self {
+    pub const DETERMINIZATIONS: u32 = 4;
+
+    pub fn new(
+        player: ContenderProperties,
+        difficulty: Difficulty,
+        personality: AiPersonality,
+        seed: u64,
+        rules: RuleSet,
+    ) -> Self {
         AiState {
             player,
+            current: player,
+            opponents: vec![],
             metrics: Metrics {
                 enemy_extracted: 0,
                 allied_extracted: 0,
@@ -79,27 +216,441 @@ impl AiState {
             },
             measures: vec![],
             difficulty,
+            difficulty_profile: DifficultyProfile {
+                sight_range: rules.ai_sight_range,
+                ..difficulty.profile()
+            },
+            personality,
+            personality_profile: personality.profile(),
+            rules,
+            seed,
+            rng: StreamRng::seed_from_u64(seed),
+            last_decision_time: 0.0,
+            next_decision_time: 0.0,
+            observed_replies: vec![],
+            search: SearchCache::default(),
+        }
+    }
+
+    pub fn snapshot(&self) -> AiSnapshot {
+        AiSnapshot {
+            player: self.player,
+            difficulty: self.difficulty,
+            personality: self.personality,
+            seed: self.seed,
+            word_pos: self.rng.get_word_pos(),
+            last_decision_time: self.last_decision_time,
+            next_decision_time: self.next_decision_time,
         }
     }
 
+    pub fn restore(snapshot: &AiSnapshot, rules: RuleSet) -> Self {
+        let mut ai = AiState::new(
+            snapshot.player,
+            snapshot.difficulty,
+            snapshot.personality,
+            snapshot.seed,
+            rules,
+        );
+        ai.rng.set_word_pos(snapshot.word_pos);
+        ai.last_decision_time = snapshot.last_decision_time;
+        ai.next_decision_time = snapshot.next_decision_time;
+        ai
+    }
+
     pub fn get_player(&self) -> ContenderProperties {
         self.player
     }
 
-    pub fn get_best_move(&self) -> PlayerAction {
-        let mut mcts = MctsManager::new(
-            self.clone(),
-            MyMcts,
-            MyEvaluator,
-            UCTPolicy::new(match &self.difficulty {
-                Difficulty::Easy => 0.2,
-                Difficulty::Medium => 0.6,
-                Difficulty::Hard => 1.0,
-            }),
-            ApproxTable::new(2048),
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn get_personality(&self) -> AiPersonality {
+        self.personality
+    }
+
+    /// Replaces the settings derived from the difficulty, as when comparing playout
+    /// budgets; they are not part of the snapshot.
+    pub fn set_difficulty_profile(&mut self, difficulty_profile: DifficultyProfile) {
+        self.difficulty_profile = difficulty_profile;
+    }
+
+    pub fn get_difficulty_profile(&self) -> DifficultyProfile {
+        self.difficulty_profile
+    }
+
+    /// Replaces the weights derived from the personality, as when tuning the
+    /// evaluation; they are not part of the snapshot.
+    pub fn set_personality_profile(&mut self, personality_profile: PersonalityProfile) {
+        self.personality_profile = personality_profile;
+    }
+
+    pub fn get_personality_profile(&self) -> PersonalityProfile {
+        self.personality_profile
+    }
+
+    /// With a rollout horizon, the measures are first played forward to score the
+    /// consequences of the moves rather than their immediate outcome.
+    /// The max^n search also scores the opponents, assuming the balanced personality
+    /// for them as theirs is unknown.
+    fn evaluate(&self) -> AiEvaluation {
+        let horizon = self.difficulty_profile.rollout_horizon;
+        let rolled_out_measures;
+        let (metrics, measures) = if horizon <= 0.0 {
+            (self.metrics.clone(), &self.measures)
+        } else {
+            let mut rng = StreamRng::seed_from_u64(self.seed ^ TranspositionHash::hash(self));
+            let mut rollout = Rollout::new(self.player.id, self.rules, &self.measures);
+            rollout.run(horizon, &mut rng);
+            rolled_out_measures = rollout.into_measures();
+            (
+                Metrics::measure(self.player.id, &rolled_out_measures),
+                &rolled_out_measures,
+            )
+        };
+        let mut evaluation = AiEvaluation::default();
+        evaluation.push(self.player.id, metrics.evaluate(&self.personality_profile));
+        if self.difficulty_profile.search_mode == SearchMode::MaxN {
+            let profile = PersonalityProfile::default();
+            for opponent in &self.opponents {
+                let score = Metrics::measure_opponent(opponent.id, measures).evaluate(&profile);
+                evaluation.push(opponent.id, score);
+            }
+        }
+        evaluation
+    }
+
+    fn pass_turn(&mut self) {
+        if self.difficulty_profile.search_mode == SearchMode::Single || self.opponents.is_empty() {
+            return;
+        }
+        self.current = match self
+            .opponents
+            .iter()
+            .position(|opponent| opponent.id == self.current.id)
+        {
+            Some(index) if index + 1 < self.opponents.len() => self.opponents[index + 1],
+            Some(_) => self.player,
+            None => self.opponents[0],
+        };
+    }
+
+    /// Opponents only reply with attacks that outnumber the defenders of a planet,
+    /// as the resources they could build ships with are already counted as ships.
+    fn opponent_moves(&self, opponent: ContenderProperties) -> Vec<PlayerAction> {
+        let planet_business = PlanetBusiness::new(self.rules);
+        let mut moves = vec![PlayerAction::Wait];
+        for from in self
+            .measures
+            .iter()
+            .filter(|m| m.planet_props.contender_id == opponent.id)
+        {
+            let ships_count = count_ships(&from.ships_by_player, opponent.id);
+            if ships_count < 2 {
+                continue;
+            }
+            for to in self
+                .measures
+                .iter()
+                .filter(|m| m.planet_props.contender_id != opponent.id)
+            {
+                let defenders_count: usize = to
+                    .ships_by_player
+                    .iter()
+                    .filter(|(player, _)| player.id != opponent.id)
+                    .map(|(_, ships)| ships.len())
+                    .sum();
+                for size in self.fleet_sizes(ships_count, Some(defenders_count)) {
+                    if planet_business.count_ships_to_move(ships_count, size) > defenders_count {
+                        moves.push(PlayerAction::MoveShips(
+                            from.planet_props,
+                            to.planet_props,
+                            size,
+                        ));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    pub fn is_ready(&self, time: f64) -> bool {
+        time >= self.next_decision_time
+    }
+
+    pub fn get_last_decision_time(&self) -> f64 {
+        self.last_decision_time
+    }
+
+    /// Records the time of the last decision and keeps the AI from deciding again
+    /// before `next_decision_time`.
+    pub fn schedule_decision(&mut self, last_decision_time: f64, next_decision_time: f64) {
+        self.last_decision_time = last_decision_time;
+        self.next_decision_time = next_decision_time;
+    }
+
+    /// Clones the AI handing the search tree over to the clone, which replaces this
+    /// AI once its search completes.
+    pub fn detach(&mut self) -> AiState {
+        let mut ai = self.clone();
+        ai.search = SearchCache(std::mem::take(&mut self.search.0));
+        ai
+    }
+
+    pub fn get_best_move(&mut self) -> PlayerAction {
+        let playouts = self.difficulty_profile.playouts;
+        self.search(MyMcts::default(), |mcts, shares| {
+            mcts.playout_n(playouts / shares as u64)
+        })
+    }
+
+    /// Searches for `budget`, on up to `max_threads` threads when the difficulty
+    /// allows a parallel search.
+    pub fn get_best_move_within(&mut self, budget: Duration, max_threads: usize) -> PlayerAction {
+        let budget = budget.mul_f64(self.difficulty_profile.budget_factor);
+        let search_threads = self.get_search_threads(max_threads);
+        if search_threads > 1 {
+            let spec = MyMcts::with_virtual_loss(MyMcts::PARALLEL_VIRTUAL_LOSS);
+            return self.search(spec, |mcts, shares| {
+                mcts.playout_parallel_for(budget / shares, search_threads)
+            });
+        }
+        self.search(MyMcts::default(), |mcts, shares| {
+            let start = Instant::now();
+            let budget = budget / shares;
+            mcts.playout_until(|| start.elapsed() >= budget)
+        })
+    }
+
+    fn get_search_threads(&self, max_threads: usize) -> usize {
+        match self.difficulty {
+            Difficulty::Hard => max_threads.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Runs the playouts on the kept search tree, or on one for each sample of the fog
+    /// of war, in which case the playouts are told how many searches share their
+    /// budget.
+    fn search<F>(&mut self, spec: MyMcts, playouts: F) -> PlayerAction
+    where
+        F: Fn(&mut MctsManager<MyMcts>, u32),
+    {
+        if self.difficulty_profile.sight_range.is_some() {
+            return self.search_determinized(spec, playouts);
+        }
+        let policy_seed = self.rng.gen();
+        let cached = std::mem::take(&mut self.search.0).pop();
+        let mut mcts = self.resume_search(cached, spec, self.clone());
+        mcts.single_threaded_data(ThreadData {
+            policy_data: PolicyRng::with_seed(policy_seed),
+            extra_data: (),
+        });
+        playouts(&mut mcts, 1);
+        let best_move = mcts.best_move().unwrap_or(PlayerAction::Wait);
+        let explored: Vec<PlayerAction> = mcts
+            .tree()
+            .root_node()
+            .moves()
+            .filter(|move_info| move_info.child().is_some())
+            .map(|move_info| *move_info.get_move())
+            .collect();
+        let best_move = self.make_mistake(best_move, &explored);
+        if mcts.advance(&best_move).is_ok() {
+            self.search.0.push(mcts);
+        }
+        best_move
+    }
+
+    /// Searches a tree for each sample of the enemy ships hidden by the fog of war
+    /// and picks the move visited the most across all of them.
+    fn search_determinized<F>(&mut self, spec: MyMcts, playouts: F) -> PlayerAction
+    where
+        F: Fn(&mut MctsManager<MyMcts>, u32),
+    {
+        let mut visits_by_move: Vec<(PlayerAction, u64)> = vec![];
+        let mut cached = std::mem::take(&mut self.search.0).into_iter();
+        let mut searches = vec![];
+        for _ in 0..Self::DETERMINIZATIONS {
+            let policy_seed = self.rng.gen();
+            let state = self.determinize();
+            let mut mcts = self.resume_search(cached.next(), spec, state);
+            mcts.single_threaded_data(ThreadData {
+                policy_data: PolicyRng::with_seed(policy_seed),
+                extra_data: (),
+            });
+            playouts(&mut mcts, Self::DETERMINIZATIONS);
+            for move_info in mcts.tree().root_node().moves() {
+                let mov = *move_info.get_move();
+                match visits_by_move.iter_mut().find(|(m, _)| *m == mov) {
+                    Some((_, visits)) => *visits += move_info.visits(),
+                    None => visits_by_move.push((mov, move_info.visits())),
+                }
+            }
+            searches.push(mcts);
+        }
+        let best_move = visits_by_move
+            .iter()
+            .max_by_key(|(_, visits)| *visits)
+            .map(|(mov, _)| *mov)
+            .unwrap_or(PlayerAction::Wait);
+        let explored: Vec<PlayerAction> = visits_by_move
+            .iter()
+            .filter(|(_, visits)| *visits > 0)
+            .map(|(mov, _)| *mov)
+            .collect();
+        let best_move = self.make_mistake(best_move, &explored);
+        self.search = SearchCache(
+            searches
+                .into_iter()
+                .filter_map(|mut mcts| mcts.advance(&best_move).ok().map(|_| mcts))
+                .collect(),
         );
-        mcts.playout_n(512);
-        mcts.best_move().unwrap_or(PlayerAction::Wait)
+        best_move
+    }
+
+    /// Continues a kept tree from `state` when it can, or starts a new one. The kept
+    /// tree is rooted after the last move of the AI, so in the modes where the
+    /// opponents reply it is first advanced through the replies observed since then,
+    /// or through waiting when an opponent did not move.
+    fn resume_search(
+        &self,
+        cached: Option<MctsManager<MyMcts>>,
+        spec: MyMcts,
+        state: AiState,
+    ) -> MctsManager<MyMcts> {
+        cached
+            .filter(|mcts| *mcts.tree().spec() == spec)
+            .and_then(|mut mcts| {
+                let replies_count = mcts.tree().root_state().opponents.len();
+                for _ in 0..replies_count {
+                    let root_state = mcts.tree().root_state();
+                    if root_state.current.id == self.player.id {
+                        break;
+                    }
+                    let reply = self.observed_reply(root_state.current.id, &mcts);
+                    mcts.advance(&reply).ok()?;
+                }
+                if mcts.tree().root_state().current.id != self.player.id {
+                    return None;
+                }
+                mcts.set_root_state(state.clone()).ok().map(|_| mcts)
+            })
+            .unwrap_or_else(|| {
+                MctsManager::new(
+                    state,
+                    spec,
+                    MyEvaluator,
+                    UCTPolicy::new(self.difficulty_profile.exploration),
+                    AiTable::new(self.difficulty_profile.verified_transpositions, 2048),
+                )
+            })
+    }
+
+    /// The most visited reply of the opponent at the root of `mcts` matching a fleet
+    /// it launched since the last decision, or waiting if none does.
+    fn observed_reply(&self, opponent_id: usize, mcts: &MctsManager<MyMcts>) -> PlayerAction {
+        mcts.tree()
+            .root_node()
+            .moves()
+            .filter(|move_info| match move_info.get_move() {
+                PlayerAction::MoveShips(from, to, _) => {
+                    self.observed_replies
+                        .contains(&(opponent_id, from.id, to.id))
+                }
+                _ => false,
+            })
+            .max_by_key(|move_info| move_info.visits())
+            .map(|move_info| *move_info.get_move())
+            .unwrap_or(PlayerAction::Wait)
+    }
+
+    /// Samples the ships on the planets out of sight, assuming each enemy planet holds
+    /// up to twice the ships of the enemy planets in sight, or of the allied ones when
+    /// none is.
+    fn determinize(&mut self) -> AiState {
+        let mut state = self.clone();
+        let player_id = self.player.id;
+        let garrisons: Vec<usize> = self
+            .measures
+            .iter()
+            .filter(|m| m.visible && m.planet_props.contender_id != usize::MAX)
+            .filter(|m| m.planet_props.contender_id != player_id)
+            .map(|m| m.ships_count.saturating_sub(m.allied_ships_count))
+            .collect();
+        let garrisons = if garrisons.is_empty() {
+            self.measures
+                .iter()
+                .filter(|m| m.planet_props.contender_id == player_id)
+                .map(|m| m.allied_ships_count)
+                .collect()
+        } else {
+            garrisons
+        };
+        let mean = garrisons.iter().sum::<usize>() as f32 / garrisons.len().max(1) as f32;
+        let max_ships_count = (2.0 * mean).round() as usize;
+        for (planet_id, measure) in state.measures.iter_mut().enumerate() {
+            let owner_id = measure.planet_props.contender_id;
+            if measure.visible || owner_id == usize::MAX || owner_id == player_id {
+                continue;
+            }
+            let ships_count = self.rng.gen_range(0..=max_ships_count);
+            let ship = VesselProperties {
+                id: usize::MAX,
+                contender_id: owner_id,
+                celestial_id: planet_id,
+            };
+            match measure
+                .ships_by_player
+                .iter_mut()
+                .find(|(player, _)| player.id == owner_id)
+            {
+                Some((_, ships)) => ships.resize(ships_count, ship),
+                None => {
+                    if let Some(owner) = self.opponents.iter().find(|player| player.id == owner_id)
+                    {
+                        measure
+                            .ships_by_player
+                            .push((*owner, vec![ship; ships_count]));
+                    }
+                }
+            }
+            measure.ships_count = measure
+                .ships_by_player
+                .iter()
+                .fold(0, |acc, (_, ships)| acc + ships.len());
+        }
+        state
+    }
+
+    /// Mistakes are picked among the moves the search explored, if any, so that the
+    /// tree kept for the next decision can follow the move played.
+    fn make_mistake(&mut self, best_move: PlayerAction, explored: &[PlayerAction]) -> PlayerAction {
+        if self.difficulty_profile.mistake_probability > 0.0
+            && self
+                .rng
+                .gen_bool(self.difficulty_profile.mistake_probability)
+        {
+            let moves = if explored.is_empty() {
+                self.available_moves()
+            } else {
+                explored.to_vec()
+            };
+            if !moves.is_empty() {
+                return moves[self.rng.gen_range(0..moves.len())];
+            }
+        }
+        best_move
+    }
+
+    fn perceive_ships_count(&mut self, ships_count: usize) -> usize {
+        let noise = self.difficulty_profile.perception_noise;
+        if noise <= 0.0 || ships_count == 0 {
+            return ships_count;
+        }
+        let error = self.rng.gen_range(-noise..=noise);
+        (ships_count as f32 * (1.0 + error)).round().max(0.0) as usize
     }
 
     pub fn refresh_measures(
@@ -107,8 +658,71 @@ impl AiState {
         planet_distances: &[Vec<f32>],
         players: &[ContenderProperties],
         ships_by_player_by_planet: Vec<(CelestialProperties, Vec<ContenderVessels>)>,
+        fleets: &[Fleet],
     ) {
-        let measures = ships_by_player_by_planet.to_vec();
+        let player_id = self.player.id;
+        self.current = self.player;
+        self.opponents = players
+            .iter()
+            .filter(|player| {
+                player.id != player_id
+                    && ships_by_player_by_planet
+                        .iter()
+                        .any(|(planet, ships_by_player)| {
+                            planet.contender_id == player.id
+                                || count_ships(ships_by_player, player.id) > 0
+                        })
+            })
+            .copied()
+            .collect();
+        let mut measures = ships_by_player_by_planet;
+        let visible: Vec<bool> = match self.difficulty_profile.sight_range {
+            Some(sight_range) => measures
+                .iter()
+                .enumerate()
+                .map(|(planet_id, (planet, ships_by_player))| {
+                    planet.contender_id == player_id
+                        || count_ships(ships_by_player, player_id) > 0
+                        || measures.iter().enumerate().any(|(other_id, (other, _))| {
+                            other.contender_id == player_id
+                                && planet_distances[other_id][planet_id] <= sight_range
+                        })
+                })
+                .collect(),
+            None => vec![true; measures.len()],
+        };
+        self.observed_replies = fleets
+            .iter()
+            .filter(|fleet| {
+                fleet.contender_id != player_id
+                    && fleet.departure_time >= self.last_decision_time
+                    && visible[fleet.origin_id]
+            })
+            .map(|fleet| (fleet.contender_id, fleet.origin_id, fleet.destination_id))
+            .collect();
+        for (planet_id, (planet, ships_by_player)) in measures.iter_mut().enumerate() {
+            if !visible[planet_id] {
+                planet.extracted = 0.0;
+            }
+            for (player, ships) in ships_by_player.iter_mut() {
+                if player.id == player_id {
+                    continue;
+                }
+                if !visible[planet_id] {
+                    ships.clear();
+                    continue;
+                }
+                let perceived_ships_count = self.perceive_ships_count(ships.len());
+                ships.resize(
+                    perceived_ships_count,
+                    VesselProperties {
+                        id: usize::MAX,
+                        contender_id: player.id,
+                        celestial_id: planet_id,
+                    },
+                );
+            }
+        }
         self.measures = measures
             .iter()
             .enumerate()
@@ -118,7 +732,7 @@ impl AiState {
                         ships_by_player.clone()
                     } else {
                         let enemy_ships_from_extracted = (0..(planet.extracted as usize))
-                            .step_by(Consts::ADD_SHIP_RESOURCE_COST as usize)
+                            .step_by(self.rules.add_ship_resource_cost as usize)
                             .map(|_| VesselProperties {
                                 id: usize::MAX,
                                 contender_id: planet.contender_id,
@@ -135,10 +749,19 @@ impl AiState {
                         .to_vec();
                         [ships_by_player.clone(), enemy_ships_from_extracted].concat()
                     };
+                let incoming_allied_ships_count = fleets
+                    .iter()
+                    .filter(|fleet| {
+                        fleet.destination_id == planet.id && fleet.contender_id == self.player.id
+                    })
+                    .map(|fleet| fleet.ships.len())
+                    .sum();
                 Measure {
                     planet_props: *planet,
+                    incoming_allied_ships_count,
                     distances: planet_distances.get(planet_id).unwrap().to_vec(),
                     distance: f32::INFINITY,
+                    visible: visible[planet_id],
                     ships_by_player: ships_by_player.clone(),
                     extracted: planet.extracted,
                     ships_count: ships_by_player
@@ -155,61 +778,95 @@ impl AiState {
             .collect();
     }
 
-    fn make_move_ships(player_id: usize, from: &mut Measure, to: &mut Measure) {
-        let planet_business = PlanetBusiness::new();
+    fn fleet_sizes(
+        &self,
+        allied_ships_count: usize,
+        target_ships_count: Option<usize>,
+    ) -> Vec<FleetSize> {
+        let planet_business = PlanetBusiness::new(self.rules);
+        let mut sizes = vec![
+            FleetSize::Percent(self.rules.move_ship_fleet_percent),
+            FleetSize::AllBut(1),
+        ];
+        if let Some(target_ships_count) = target_ships_count {
+            sizes.push(FleetSize::Count(target_ships_count + 1));
+        }
+
+        let mut counts = vec![];
+        sizes.retain(|size| {
+            let count = planet_business.count_ships_to_move(allied_ships_count, *size);
+            if count == 0 || count >= allied_ships_count || counts.contains(&count) {
+                return false;
+            }
+            counts.push(count);
+            true
+        });
+        sizes
+    }
+
+    fn make_move_ships(
+        player_id: usize,
+        mover_id: usize,
+        rules: RuleSet,
+        from: &mut Measure,
+        to: &mut Measure,
+        size: FleetSize,
+        rng: &mut StreamRng,
+    ) {
+        let planet_business = PlanetBusiness::new(rules);
         let allied_ships: &mut Vec<VesselProperties> = from
             .ships_by_player
             .iter_mut()
             .find_map(|(player, ships)| {
-                if player.id == player_id {
+                if player.id == mover_id {
                     return Some(ships);
                 }
                 None
             })
             .unwrap();
-        let count: usize = planet_business
-            .count_ships_to_move(allied_ships.len(), Consts::MOVE_SHIP_FLEET_PERCENT);
+        let count: usize = planet_business.count_ships_to_move(allied_ships.len(), size);
         if count > 0 {
             let (_, allied_ships_on_planet) = to
                 .ships_by_player
                 .iter_mut()
-                .find(|(player, _)| player.id == player_id)
+                .find(|(player, _)| player.id == mover_id)
                 .unwrap();
 
             allied_ships
                 .drain(0..count)
                 .for_each(|allied_ship| allied_ships_on_planet.push(allied_ship));
 
-            let (winner, casualties) = planet_business.battle(to.ships_by_player.to_vec());
-            from.distance = 0.0;
-            to.distance = *to.distances.get(from.planet_props.id).unwrap();
+            let result = planet_business.battle(to.ships_by_player.to_vec(), rng);
+            if mover_id == player_id {
+                from.distance = 0.0;
+                to.distance = *to.distances.get(from.planet_props.id).unwrap();
+            }
             from.ships_count = from
                 .ships_by_player
                 .iter()
                 .fold(0, |acc, (_, ships)| acc + ships.len());
-            from.allied_ships_count =
-                from.ships_by_player.iter().fold(0, |acc, (player, ships)| {
-                    if player.id == player_id {
-                        return acc + ships.len();
-                    }
-                    acc
-                }) - count;
+            if mover_id == player_id {
+                from.allied_ships_count =
+                    from.ships_by_player.iter().fold(0, |acc, (player, ships)| {
+                        if player.id == player_id {
+                            return acc + ships.len();
+                        }
+                        acc
+                    });
+            }
             to.ships_count = to
                 .ships_by_player
                 .iter()
                 .fold(0, |acc, (_, ships)| acc + ships.len())
-                - casualties.len();
+                - result.get_casualties().len();
             to.allied_ships_count = to.ships_by_player.iter().fold(0, |acc, (player, ships)| {
                 if player.id == player_id {
                     return acc + ships.len();
                 }
                 acc
-            }) - casualties
-                .iter()
-                .filter(|c| c.contender_id == player_id)
-                .count();
-            if let Some(winner) = winner {
-                if winner.id == player_id {
+            }) - result.get_casualties_count(player_id);
+            if let Some(winner) = result.winner {
+                if winner.id == mover_id {
                     to.planet_props.contender_id = winner.id;
                 }
             }
@@ -223,9 +880,14 @@ impl GameState for AiState {
     type MoveList = Vec<PlayerAction>;
 
     fn current_player(&self) -> Self::Player {
-        self.player
+        self.current
     }
     fn available_moves(&self) -> Vec<PlayerAction> {
+        if self.current.id != self.player.id {
+            return self.opponent_moves(self.current);
+        }
+        let personality = self.personality_profile;
+        let build_cost = self.rules.add_ship_resource_cost * personality.build_reserve_ratio;
         let mut moves = vec![];
         let allied_planets: Vec<&Measure> = self
             .measures
@@ -240,13 +902,13 @@ impl GameState for AiState {
 
         if allied_planets
             .iter()
-            .all(|planet| planet.extracted < Consts::ADD_SHIP_RESOURCE_COST)
+            .all(|planet| planet.extracted < build_cost)
         {
             moves.push(PlayerAction::Wait);
         }
 
         allied_planets.iter().for_each(|planet| {
-            if planet.extracted > Consts::ADD_SHIP_RESOURCE_COST {
+            if planet.extracted > build_cost {
                 moves.push(PlayerAction::AddShip(planet.planet_props))
             }
         });
@@ -274,21 +936,42 @@ impl GameState for AiState {
                         }
                     })
                     .sum();
-                if allied_ships_on_planet > 1 && enemy_ships_on_planet < 2 * allied_ships_on_planet
+                if allied_ships_on_planet > 1
+                    && (enemy_ships_on_planet as f32)
+                        < personality.reinforce_enemy_ratio * allied_ships_on_planet as f32
                 {
-                    moves.push(PlayerAction::MoveShips(
-                        allied_planets[i].planet_props,
-                        allied_planets[j].planet_props,
-                    ));
+                    for size in self.fleet_sizes(allied_ships_on_planet, None) {
+                        moves.push(PlayerAction::MoveShips(
+                            allied_planets[i].planet_props,
+                            allied_planets[j].planet_props,
+                            size,
+                        ));
+                    }
                 }
             }
         }
         for allied_planet in &allied_planets {
-            for enemy_planet in &enemy_planets {
-                moves.push(PlayerAction::MoveShips(
-                    allied_planet.planet_props,
-                    enemy_planet.planet_props,
-                ));
+            for enemy_planet in enemy_planets.iter().filter(|m| {
+                m.incoming_allied_ships_count as f32
+                    <= personality.attack_incoming_ratio * m.ships_count as f32
+            }) {
+                let enemy_ships_count = enemy_planet
+                    .ships_count
+                    .saturating_sub(enemy_planet.allied_ships_count);
+                if (allied_planet.allied_ships_count as f32)
+                    < personality.attack_advantage_ratio * enemy_ships_count as f32
+                {
+                    continue;
+                }
+                for size in
+                    self.fleet_sizes(allied_planet.allied_ships_count, Some(enemy_ships_count))
+                {
+                    moves.push(PlayerAction::MoveShips(
+                        allied_planet.planet_props,
+                        enemy_planet.planet_props,
+                        size,
+                    ));
+                }
             }
         }
 
@@ -302,12 +985,12 @@ impl GameState for AiState {
                     .iter_mut()
                     .find(|m| m.planet_props.id == on.id)
                     .unwrap();
-                measure.extracted -= Consts::ADD_SHIP_RESOURCE_COST;
+                measure.extracted -= self.rules.add_ship_resource_cost;
                 measure.distance = 0.1;
                 measure.ships_count += 1;
                 measure.allied_ships_count += 1;
             }
-            PlayerAction::MoveShips(from, to) => {
+            PlayerAction::MoveShips(from, to, size) => {
                 let measures = &self.measures;
                 let measure_from = measures
                     .iter()
@@ -331,54 +1014,89 @@ impl GameState for AiState {
                 let first_measure = &mut head[first_index];
                 let second_measure = &mut tail[second_index - first_index - 1];
                 if measure_from < measure_to {
-                    Self::make_move_ships(self.player.id, first_measure, second_measure);
+                    Self::make_move_ships(
+                        self.player.id,
+                        self.current.id,
+                        self.rules,
+                        first_measure,
+                        second_measure,
+                        size,
+                        &mut self.rng,
+                    );
                 } else {
-                    Self::make_move_ships(self.player.id, second_measure, first_measure);
+                    Self::make_move_ships(
+                        self.player.id,
+                        self.current.id,
+                        self.rules,
+                        second_measure,
+                        first_measure,
+                        size,
+                        &mut self.rng,
+                    );
                 }
             }
             PlayerAction::Wait => (),
         }
 
-        let measures = &self.measures;
-        let allied_measures: Vec<&Measure> = measures
-            .iter()
-            .filter(|m| m.planet_props.contender_id == self.player.id)
-            .collect();
-        let enemy_measures: Vec<&Measure> = measures
-            .iter()
-            .filter(|m| m.planet_props.contender_id != self.player.id)
-            .collect();
+        self.metrics = Metrics::measure(self.player.id, &self.measures);
+        self.pass_turn();
+    }
+}
 
-        let allied_extracted = allied_measures
-            .iter()
-            .map(|m| m.extracted as f32)
-            .fold(0.0, |acc, r| acc + r.floor());
-        let enemy_extracted = enemy_measures
-            .iter()
-            .map(|m| (m.planet_props.extracted + m.planet_props.resources) / m.distance)
-            .fold(0.0, |acc, r| acc + r.floor());
-        let allied_ships_count = measures
-            .iter()
-            .map(|m| m.allied_ships_count as f32)
-            .fold(0.0, |acc, s| acc + s);
-        let total_ships_count = measures
-            .iter()
-            .map(|m| m.ships_count)
-            .fold(0.0, |acc, s| acc + s as f32);
-        self.metrics = Metrics {
-            allied_extracted: allied_extracted.floor() as i64,
-            enemy_extracted: enemy_extracted.floor() as i64,
-            ships_count: allied_ships_count.floor() as i64,
-            ships_count_ratio: (100.0 * allied_ships_count / total_ships_count).floor() as i64,
-            planets_ratio: (100.0 * allied_measures.len() as f32 / measures.len() as f32) as i64,
-        };
+/// Draws the Zobrist key of a planet feature by mixing the planet, the feature and
+/// its value, in place of a table of random keys, as ship counts have no bound.
+fn zobrist_key(planet_index: usize, feature: usize, value: u64) -> u64 {
+    splitmix64(splitmix64(splitmix64(planet_index as u64) ^ feature as u64) ^ value)
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl AiState {
+    /// The owner, the allied and enemy ships and the ships that the extracted
+    /// resources can build, for each planet.
+    fn planet_features(&self) -> impl Iterator<Item = [u64; 4]> + '_ {
+        self.measures.iter().map(move |m| {
+            [
+                m.planet_props.contender_id as u64,
+                m.allied_ships_count as u64,
+                m.ships_count.saturating_sub(m.allied_ships_count) as u64,
+                (m.extracted / self.rules.add_ship_resource_cost).floor() as u64,
+            ]
+        })
     }
 }
 
 impl TranspositionHash for AiState {
     fn hash(&self) -> u64 {
-        let mut s = DefaultHasher::new();
-        self.metrics.hash(&mut s);
-        s.finish()
+        let mut hash =
+            self.planet_features()
+                .enumerate()
+                .fold(0, |hash, (planet_index, features)| {
+                    features
+                        .iter()
+                        .enumerate()
+                        .fold(hash, |hash, (feature, value)| {
+                            hash ^ zobrist_key(planet_index, feature, *value)
+                        })
+                });
+        if self.difficulty_profile.search_mode != SearchMode::Single {
+            hash ^= splitmix64(!(self.current.id as u64));
+        }
+        hash
+    }
+}
+
+impl TranspositionKey for AiState {
+    type Key = Vec<u64>;
+
+    fn key(&self) -> Vec<u64> {
+        let mut key: Vec<u64> = self.planet_features().flatten().collect();
+        key.push(self.current.id as u64);
+        key
     }
 }
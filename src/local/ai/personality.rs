@@ -0,0 +1,97 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiPersonality {
+    Aggressor,
+    Turtle,
+    Expander,
+    Opportunist,
+    #[default]
+    Balanced,
+}
+
+/// Weights are exponents of the strategy terms multiplied by `Metrics::evaluate`,
+/// so that all of them at 1.0 leave the evaluation unchanged.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersonalityProfile {
+    pub disadvantage_weight: f32,
+    pub advantage_weight: f32,
+    pub extraction_weight: f32,
+    pub reinforce_enemy_ratio: f32,
+    pub attack_advantage_ratio: f32,
+    pub attack_incoming_ratio: f32,
+    pub build_reserve_ratio: f32,
+}
+
+impl Default for PersonalityProfile {
+    fn default() -> Self {
+        AiPersonality::Balanced.profile()
+    }
+}
+
+impl AiPersonality {
+    pub const ALL: [AiPersonality; 5] = [
+        AiPersonality::Aggressor,
+        AiPersonality::Turtle,
+        AiPersonality::Expander,
+        AiPersonality::Opportunist,
+        AiPersonality::Balanced,
+    ];
+
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len())]
+    }
+
+    pub fn profile(&self) -> PersonalityProfile {
+        match self {
+            AiPersonality::Aggressor => PersonalityProfile {
+                disadvantage_weight: 0.5,
+                advantage_weight: 1.0,
+                extraction_weight: 1.5,
+                reinforce_enemy_ratio: 1.0,
+                attack_advantage_ratio: 0.0,
+                attack_incoming_ratio: 2.0,
+                build_reserve_ratio: 1.0,
+            },
+            AiPersonality::Turtle => PersonalityProfile {
+                disadvantage_weight: 1.5,
+                advantage_weight: 0.5,
+                extraction_weight: 0.5,
+                reinforce_enemy_ratio: 4.0,
+                attack_advantage_ratio: 1.5,
+                attack_incoming_ratio: 1.0,
+                build_reserve_ratio: 1.0,
+            },
+            AiPersonality::Expander => PersonalityProfile {
+                disadvantage_weight: 1.0,
+                advantage_weight: 1.5,
+                extraction_weight: 0.5,
+                reinforce_enemy_ratio: 2.0,
+                attack_advantage_ratio: 0.5,
+                attack_incoming_ratio: 1.0,
+                build_reserve_ratio: 1.0,
+            },
+            AiPersonality::Opportunist => PersonalityProfile {
+                disadvantage_weight: 1.0,
+                advantage_weight: 1.0,
+                extraction_weight: 1.5,
+                reinforce_enemy_ratio: 2.0,
+                attack_advantage_ratio: 1.0,
+                attack_incoming_ratio: 0.5,
+                build_reserve_ratio: 2.0,
+            },
+            AiPersonality::Balanced => PersonalityProfile {
+                disadvantage_weight: 1.0,
+                advantage_weight: 1.0,
+                extraction_weight: 1.0,
+                reinforce_enemy_ratio: 2.0,
+                attack_advantage_ratio: 0.0,
+                attack_incoming_ratio: 1.0,
+                build_reserve_ratio: 1.0,
+            },
+        }
+    }
+}
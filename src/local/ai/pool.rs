@@ -0,0 +1,172 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::AiState;
+use crate::local::player::PlayerAction;
+
+struct AiRequest {
+    generation: u64,
+    ai: AiState,
+}
+
+struct AiResponse {
+    generation: u64,
+    ai: AiState,
+    action: PlayerAction,
+}
+
+/// Searches AI moves on background threads, so that the game thread only submits
+/// `AiState`s and polls the resulting `PlayerAction`s without waiting for them.
+/// `new` returns `None` where threads cannot be spawned, as in the HTML5 build,
+/// and the caller falls back to searching on the game thread. The cores are
+/// split across the workers, so that the parallel searches of the hard AIs do not
+/// oversubscribe the machine. The workers skip the requests queued before the last
+/// `cancel`, as after a reset or a load they would only delay the next game.
+pub struct AiWorkerPool {
+    requests: Option<Sender<AiRequest>>,
+    responses: Receiver<AiResponse>,
+    workers: Vec<JoinHandle<()>>,
+    generation: Arc<AtomicU64>,
+    pending: Vec<usize>,
+}
+
+impl AiWorkerPool {
+    pub fn new(workers_count: usize, budget: Duration) -> Option<Self> {
+        let (requests, request_receiver) = channel::<AiRequest>();
+        let (response_sender, responses) = channel();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+        let workers_count = workers_count.max(1);
+        let search_threads = Self::search_threads_count(workers_count);
+        let current_generation = Arc::new(AtomicU64::new(0));
+        let workers = (0..workers_count)
+            .map(|index| {
+                let request_receiver = request_receiver.clone();
+                let response_sender = response_sender.clone();
+                let current_generation = current_generation.clone();
+                thread::Builder::new()
+                    .name(format!("ai-worker-{index}"))
+                    .spawn(move || loop {
+                        let request = request_receiver.lock().unwrap().recv();
+                        let AiRequest { generation, mut ai } = match request {
+                            Ok(request) => request,
+                            Err(_) => return,
+                        };
+                        if generation != current_generation.load(Ordering::SeqCst) {
+                            continue;
+                        }
+                        let action = ai.get_best_move_within(budget, search_threads);
+                        let response = AiResponse {
+                            generation,
+                            ai,
+                            action,
+                        };
+                        if response_sender.send(response).is_err() {
+                            return;
+                        }
+                    })
+                    .ok()
+            })
+            .collect::<Option<Vec<JoinHandle<()>>>>()?;
+        Some(AiWorkerPool {
+            requests: Some(requests),
+            responses,
+            workers,
+            generation: current_generation,
+            pending: vec![],
+        })
+    }
+
+    pub fn default_workers_count() -> usize {
+        thread::available_parallelism()
+            .map(|count| count.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Threads each worker may use for a single search.
+    pub fn search_threads_count(workers_count: usize) -> usize {
+        thread::available_parallelism()
+            .map(|count| count.get() / workers_count.max(1))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    pub fn is_pending(&self, player_id: usize) -> bool {
+        self.pending.contains(&player_id)
+    }
+
+    pub fn submit(&mut self, ai: AiState) {
+        let player_id = ai.get_player().id;
+        let request = AiRequest {
+            generation: self.generation.load(Ordering::SeqCst),
+            ai,
+        };
+        if let Some(requests) = &self.requests {
+            if requests.send(request).is_ok() {
+                self.pending.push(player_id);
+            }
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<(AiState, PlayerAction)> {
+        let mut results = vec![];
+        while let Ok(response) = self.responses.try_recv() {
+            if response.generation != self.generation.load(Ordering::SeqCst) {
+                continue;
+            }
+            let player_id = response.ai.get_player().id;
+            self.pending.retain(|id| *id != player_id);
+            results.push((response.ai, response.action));
+        }
+        results
+    }
+
+    pub fn cancel(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.pending.clear();
+    }
+}
+
+impl Drop for AiWorkerPool {
+    fn drop(&mut self) {
+        self.requests.take();
+        self.workers.drain(..).for_each(|worker| {
+            let _ = worker.join();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::sim::SimGame;
+    use std::time::Instant;
+
+    #[test]
+    fn cancelled_requests_are_skipped() {
+        let mut game = SimGame::demo(2, 3, 1);
+        game.tick(1.0);
+        let ai = game.get_game_state().get_ais()[0].clone();
+        let budget = Duration::from_millis(400);
+        let search_budget = budget.mul_f64(ai.get_difficulty_profile().budget_factor);
+        let mut pool = AiWorkerPool::new(1, budget).unwrap();
+
+        let stale_count = 8;
+        for _ in 0..stale_count {
+            pool.submit(ai.clone());
+        }
+        pool.cancel();
+        let start = Instant::now();
+        pool.submit(ai.clone());
+        while pool.poll().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Only the stale search already running may delay the new one.
+        assert!(start.elapsed() < search_budget * 4);
+        assert!(!pool.is_pending(ai.get_player().id));
+    }
+}
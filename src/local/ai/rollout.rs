@@ -0,0 +1,250 @@
+use super::{count_ships, ContenderVessels, Measure};
+use crate::local::clock::SimClock;
+use crate::local::model::*;
+use crate::local::planet::PlanetBusiness;
+use crate::local::player::FleetSize;
+use crate::local::rng::StreamRng;
+use crate::local::ruleset::RuleSet;
+use crate::local::EXTRACTION_INTERVAL;
+
+struct RolloutFleet {
+    contender: ContenderProperties,
+    destination: usize,
+    ships: Vec<VesselProperties>,
+    arrival_time: f64,
+}
+
+/// Plays the measured planets forward over a horizon, one extraction interval at a
+/// time: fleets travel and land, battles are fought, planets extract resources and
+/// their owners build ships with them. As a default policy, each player then sends
+/// a fleet from its largest garrison to the nearest planet it can overwhelm.
+pub(super) struct Rollout {
+    player_id: usize,
+    rules: RuleSet,
+    measures: Vec<Measure>,
+    businesses: Vec<PlanetBusiness>,
+    fleets: Vec<RolloutFleet>,
+    time: f64,
+}
+
+impl Rollout {
+    pub(super) fn new(player_id: usize, rules: RuleSet, measures: &[Measure]) -> Self {
+        let measures: Vec<Measure> = measures
+            .iter()
+            .map(|measure| {
+                let mut measure = measure.clone();
+                measure.ships_by_player = Self::merge_ships_by_player(&measure.ships_by_player);
+                // The ships estimated from the resources of enemy planets already
+                // account for what they have extracted
+                let owner_id = measure.planet_props.contender_id;
+                if owner_id != player_id && owner_id != usize::MAX {
+                    measure.extracted = 0.0;
+                }
+                measure
+            })
+            .collect();
+        Rollout {
+            player_id,
+            rules,
+            businesses: measures
+                .iter()
+                .map(|_| PlanetBusiness::new(rules))
+                .collect(),
+            measures,
+            fleets: vec![],
+            time: 0.0,
+        }
+    }
+
+    fn merge_ships_by_player(ships_by_player: &[ContenderVessels]) -> Vec<ContenderVessels> {
+        let mut merged: Vec<ContenderVessels> = vec![];
+        for (player, ships) in ships_by_player {
+            match merged.iter_mut().find(|(p, _)| p.id == player.id) {
+                Some((_, merged_ships)) => merged_ships.extend_from_slice(ships),
+                None => merged.push((*player, ships.clone())),
+            }
+        }
+        merged
+    }
+
+    pub(super) fn run(&mut self, horizon: f64, rng: &mut StreamRng) {
+        while self.time < horizon {
+            self.time += EXTRACTION_INTERVAL;
+            self.land_fleets();
+            self.battle(rng);
+            self.extract();
+            self.react();
+        }
+    }
+
+    /// Returns the measures at the end of the rollout, counting the allied ships
+    /// still travelling on their destination planets.
+    pub(super) fn into_measures(self) -> Vec<Measure> {
+        let player_id = self.player_id;
+        let mut measures = self.measures;
+        for measure in measures.iter_mut() {
+            measure.ships_count = measure
+                .ships_by_player
+                .iter()
+                .map(|(_, ships)| ships.len())
+                .sum();
+            measure.allied_ships_count = count_ships(&measure.ships_by_player, player_id);
+        }
+        for fleet in self.fleets {
+            let measure = &mut measures[fleet.destination];
+            measure.ships_count += fleet.ships.len();
+            if fleet.contender.id == player_id {
+                measure.allied_ships_count += fleet.ships.len();
+            }
+        }
+        measures
+    }
+
+    fn count_defenders(measure: &Measure, contender_id: usize) -> usize {
+        measure
+            .ships_by_player
+            .iter()
+            .filter(|(player, _)| player.id != contender_id)
+            .map(|(_, ships)| ships.len())
+            .sum()
+    }
+
+    fn land_fleets(&mut self) {
+        let time = self.time;
+        let measures = &mut self.measures;
+        self.fleets.retain_mut(|fleet| {
+            if fleet.arrival_time > time {
+                return true;
+            }
+            let ships_by_player = &mut measures[fleet.destination].ships_by_player;
+            match ships_by_player
+                .iter_mut()
+                .find(|(player, _)| player.id == fleet.contender.id)
+            {
+                Some((_, ships)) => ships.append(&mut fleet.ships),
+                None => ships_by_player.push((fleet.contender, fleet.ships.split_off(0))),
+            }
+            false
+        });
+    }
+
+    fn battle(&mut self, rng: &mut StreamRng) {
+        let ticks = (EXTRACTION_INTERVAL / SimClock::TICK_DURATION).round() as usize;
+        for (measure, business) in self.measures.iter_mut().zip(self.businesses.iter()) {
+            for _ in 0..ticks {
+                let owner_id = measure.planet_props.contender_id;
+                let is_contested = measure
+                    .ships_by_player
+                    .iter()
+                    .any(|(player, ships)| player.id != owner_id && !ships.is_empty());
+                if !is_contested {
+                    break;
+                }
+                let result = business.battle(measure.ships_by_player.to_vec(), rng);
+                for (player, ships) in measure.ships_by_player.iter_mut() {
+                    let casualties_count = result.get_casualties_count(player.id);
+                    ships.truncate(ships.len().saturating_sub(casualties_count));
+                }
+                if let Some(winner) = result.winner {
+                    measure.planet_props.contender_id = winner.id;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn extract(&mut self) {
+        let cost = self.rules.add_ship_resource_cost;
+        for (measure, business) in self.measures.iter_mut().zip(self.businesses.iter()) {
+            let owner_id = measure.planet_props.contender_id;
+            if owner_id == usize::MAX {
+                continue;
+            }
+            let planet_id = measure.planet_props.id;
+            let orbiters_count = count_ships(&measure.ships_by_player, owner_id) as i32;
+            measure.planet_props.extracted = measure.extracted;
+            business.resources_update(&mut measure.planet_props, orbiters_count);
+            if let Some((_, ships)) = measure
+                .ships_by_player
+                .iter_mut()
+                .find(|(player, _)| player.id == owner_id)
+            {
+                while measure.planet_props.extracted >= cost {
+                    measure.planet_props.extracted -= cost;
+                    ships.push(VesselProperties {
+                        id: usize::MAX,
+                        contender_id: owner_id,
+                        celestial_id: planet_id,
+                    });
+                }
+            }
+            measure.extracted = measure.planet_props.extracted;
+        }
+    }
+
+    fn react(&mut self) {
+        let speed = self.rules.fleet_speed();
+        let mut owners: Vec<usize> = self
+            .measures
+            .iter()
+            .map(|measure| measure.planet_props.contender_id)
+            .filter(|owner_id| *owner_id != usize::MAX)
+            .collect();
+        owners.sort_unstable();
+        owners.dedup();
+
+        let planet_business = PlanetBusiness::new(self.rules);
+        for owner_id in owners {
+            let origin = self
+                .measures
+                .iter()
+                .enumerate()
+                .filter(|(_, measure)| measure.planet_props.contender_id == owner_id)
+                .max_by_key(|(_, measure)| count_ships(&measure.ships_by_player, owner_id));
+            let (origin, garrison) = match origin {
+                Some((origin, measure)) => {
+                    (origin, count_ships(&measure.ships_by_player, owner_id))
+                }
+                None => continue,
+            };
+            let count = planet_business.count_ships_to_move(
+                garrison,
+                FleetSize::Percent(self.rules.move_ship_fleet_percent),
+            );
+            if count == 0 || count >= garrison {
+                continue;
+            }
+            let distances = &self.measures[origin].distances;
+            let target = self
+                .measures
+                .iter()
+                .enumerate()
+                .filter(|(destination, measure)| {
+                    measure.planet_props.contender_id != owner_id
+                        && !self.fleets.iter().any(|fleet| {
+                            fleet.contender.id == owner_id && fleet.destination == *destination
+                        })
+                        && Self::count_defenders(measure, owner_id) < count
+                })
+                .map(|(destination, measure)| {
+                    let distance =
+                        (distances[measure.planet_props.id] - measure.planet_props.radius).max(0.0);
+                    (destination, distance)
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((destination, distance)) = target {
+                let (contender, ships) = self.measures[origin]
+                    .ships_by_player
+                    .iter_mut()
+                    .find(|(player, _)| player.id == owner_id)
+                    .unwrap();
+                self.fleets.push(RolloutFleet {
+                    contender: *contender,
+                    destination,
+                    ships: ships.drain(0..count).collect(),
+                    arrival_time: self.time + (distance / speed) as f64,
+                });
+            }
+        }
+    }
+}
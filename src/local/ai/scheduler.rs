@@ -0,0 +1,62 @@
+use super::AiState;
+use crate::local::fleet::Fleet;
+use crate::local::starmap::Starmap;
+
+/// Decides which AIs think on a given tick. Each AI decides at its own interval,
+/// shifted by a phase drawn from its index so that the AIs of a lobby do not all
+/// think on the same frame, and waits for the fleets launched by its last action
+/// to land. The distances between planets, which never change during a game, are
+/// measured once per starmap.
+#[derive(Default)]
+pub struct AiScheduler {
+    planet_distances: Vec<Vec<f32>>,
+}
+
+impl AiScheduler {
+    pub const DECISION_INTERVAL: f64 = 0.25;
+
+    /// The fractional part of the golden ratio, which spreads the phases of any
+    /// number of AIs evenly over their intervals.
+    const PHASE_STEP: f64 = 0.618_033_988_749_895;
+
+    pub fn cache_planet_distances<T: Starmap>(&mut self, starmap: &T) {
+        let planets = starmap.get_planets();
+        self.planet_distances = planets
+            .iter()
+            .map(|from| {
+                planets
+                    .iter()
+                    .map(|to| T::get_distance_between(from, to))
+                    .collect()
+            })
+            .collect();
+    }
+
+    pub fn get_planet_distances(&self) -> &[Vec<f32>] {
+        &self.planet_distances
+    }
+
+    pub fn get_decision_interval(ai: &AiState) -> f64 {
+        Self::DECISION_INTERVAL + ai.get_difficulty_profile().reaction_delay
+    }
+
+    /// Delays the first decision of the AI added at `index` by its phase.
+    pub fn stagger(ai: &mut AiState, index: usize, time: f64) {
+        let phase = (index as f64 * Self::PHASE_STEP).fract();
+        let next_decision_time = time + phase * Self::get_decision_interval(ai);
+        ai.schedule_decision(ai.get_last_decision_time(), next_decision_time);
+    }
+
+    pub fn is_due(ai: &AiState, time: f64, fleets: &[Fleet]) -> bool {
+        let player_id = ai.get_player().id;
+        let last_decision_time = ai.get_last_decision_time();
+        ai.is_ready(time)
+            && !fleets.iter().any(|fleet| {
+                fleet.contender_id == player_id && fleet.departure_time >= last_decision_time
+            })
+    }
+
+    pub fn schedule(ai: &mut AiState, time: f64) {
+        ai.schedule_decision(time, time + Self::get_decision_interval(ai));
+    }
+}
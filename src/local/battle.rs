@@ -0,0 +1,350 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::model::*;
+use super::ruleset::RuleSet;
+
+pub type ContenderVessels = (ContenderProperties, Vec<VesselProperties>);
+
+const DICE_PER_CONTENDER: usize = 3;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleModel {
+    #[default]
+    Stochastic,
+    Lanchester,
+    Dice,
+}
+
+impl BattleModel {
+    pub fn resolver(&self, rules: RuleSet) -> Box<dyn BattleResolver> {
+        match self {
+            BattleModel::Stochastic => Box::new(StochasticResolver {
+                fighting_factor: rules.battle_fighting_factor,
+            }),
+            BattleModel::Lanchester => Box::new(LanchesterResolver {
+                fighting_factor: rules.battle_fighting_factor,
+            }),
+            BattleModel::Dice => Box::new(DiceResolver {
+                fighting_factor: rules.battle_fighting_factor,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BattleState {
+    pub damage: Vec<(usize, f32)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BattleResult {
+    pub winner: Option<ContenderProperties>,
+    pub casualties: Vec<ContenderVessels>,
+}
+
+impl BattleResult {
+    fn new(ships_by_player: &[ContenderVessels], casualties: Vec<ContenderVessels>) -> Self {
+        let remaining_players: Vec<ContenderProperties> = ships_by_player
+            .iter()
+            .filter_map(|(player, ships)| {
+                let player_casualties = casualties
+                    .iter()
+                    .filter(|(contender, _)| contender.id == player.id)
+                    .map(|(_, ships)| ships.len())
+                    .sum();
+                if ships.len().saturating_sub(player_casualties) > 0 {
+                    return Some(*player);
+                }
+                None
+            })
+            .collect();
+        let winner = if remaining_players.len() == 1 {
+            Some(remaining_players[0])
+        } else {
+            None
+        };
+        BattleResult { winner, casualties }
+    }
+
+    pub fn get_casualties(&self) -> Vec<VesselProperties> {
+        self.casualties
+            .iter()
+            .flat_map(|(_, ships)| ships.iter().copied())
+            .collect()
+    }
+
+    pub fn get_casualties_count(&self, contender_id: usize) -> usize {
+        self.casualties
+            .iter()
+            .filter(|(contender, _)| contender.id == contender_id)
+            .map(|(_, ships)| ships.len())
+            .sum()
+    }
+}
+
+pub trait BattleResolver {
+    fn resolve(
+        &self,
+        ships_by_player: &[ContenderVessels],
+        state: &mut BattleState,
+        rng: &mut dyn RngCore,
+    ) -> BattleResult;
+}
+
+pub struct StochasticResolver {
+    fighting_factor: f32,
+}
+
+impl BattleResolver for StochasticResolver {
+    fn resolve(
+        &self,
+        ships_by_player: &[ContenderVessels],
+        _state: &mut BattleState,
+        rng: &mut dyn RngCore,
+    ) -> BattleResult {
+        let total_ship_count: usize = ships_by_player.iter().map(|(_, ships)| ships.len()).sum();
+        let ship_loss_probs: Vec<f32> = ships_by_player
+            .iter()
+            .map(|(_, ships)| {
+                if total_ship_count == 0 {
+                    return 0.0;
+                }
+                let fighting_time_factor =
+                    self.fighting_factor * (1.0 - (-(total_ship_count as f32).ln()).exp());
+                1.0 - (-((total_ship_count - ships.len()) as f32 * fighting_time_factor + 1.0).ln())
+                    .exp()
+            })
+            .collect();
+
+        let mut casualties = vec![];
+        ship_loss_probs
+            .iter()
+            .enumerate()
+            .for_each(|(index, prob)| {
+                let dice = rng.gen_range(0.0..1.0);
+                let (player, ships) = &ships_by_player[index];
+                if dice > 1.0 - *prob && !ships.is_empty() {
+                    let rnd_index = rng.gen_range(0.0..(ships.len() as f32)).floor() as usize;
+                    casualties.push((*player, vec![ships[rnd_index]]));
+                }
+            });
+        BattleResult::new(ships_by_player, casualties)
+    }
+}
+
+pub struct LanchesterResolver {
+    fighting_factor: f32,
+}
+
+impl BattleResolver for LanchesterResolver {
+    fn resolve(
+        &self,
+        ships_by_player: &[ContenderVessels],
+        state: &mut BattleState,
+        _rng: &mut dyn RngCore,
+    ) -> BattleResult {
+        let total_ship_count: usize = ships_by_player.iter().map(|(_, ships)| ships.len()).sum();
+        let mut casualties = vec![];
+        let mut damage = vec![];
+        for (player, ships) in ships_by_player
+            .iter()
+            .filter(|(_, ships)| !ships.is_empty())
+        {
+            let enemy_ship_count = total_ship_count - ships.len();
+            if enemy_ship_count == 0 {
+                continue;
+            }
+            let previous = state
+                .damage
+                .iter()
+                .find(|(contender_id, _)| *contender_id == player.id)
+                .map(|(_, damage)| *damage)
+                .unwrap_or(0.0);
+            let current = previous + self.fighting_factor * enemy_ship_count as f32;
+            let losses = (current.floor() as usize).min(ships.len());
+            if losses > 0 {
+                casualties.push((*player, ships[0..losses].to_vec()));
+            }
+            damage.push((player.id, current - losses as f32));
+        }
+        state.damage = damage;
+        BattleResult::new(ships_by_player, casualties)
+    }
+}
+
+pub struct DiceResolver {
+    fighting_factor: f32,
+}
+
+impl BattleResolver for DiceResolver {
+    fn resolve(
+        &self,
+        ships_by_player: &[ContenderVessels],
+        _state: &mut BattleState,
+        rng: &mut dyn RngCore,
+    ) -> BattleResult {
+        let fighting: Vec<&ContenderVessels> = ships_by_player
+            .iter()
+            .filter(|(_, ships)| !ships.is_empty())
+            .collect();
+        let total_ship_count: usize = fighting.iter().map(|(_, ships)| ships.len()).sum();
+        if fighting.len() < 2
+            || rng.gen_range(0.0..1.0) >= self.fighting_factor * total_ship_count as f32
+        {
+            return BattleResult::new(ships_by_player, vec![]);
+        }
+
+        let rolls: Vec<Vec<u8>> = fighting
+            .iter()
+            .map(|(_, ships)| {
+                let mut dice: Vec<u8> = (0..ships.len().min(DICE_PER_CONTENDER))
+                    .map(|_| rng.gen_range(1..=6))
+                    .collect();
+                dice.sort_unstable_by(|a, b| b.cmp(a));
+                dice
+            })
+            .collect();
+        let compared = rolls.iter().map(|dice| dice.len()).min().unwrap_or(0);
+        let mut losses = vec![0; fighting.len()];
+        for position in 0..compared {
+            let best = rolls.iter().map(|dice| dice[position]).max().unwrap();
+            rolls.iter().enumerate().for_each(|(index, dice)| {
+                if dice[position] < best {
+                    losses[index] += 1;
+                }
+            });
+        }
+
+        let casualties = fighting
+            .iter()
+            .zip(losses)
+            .filter(|(_, losses)| *losses > 0)
+            .map(|((player, ships), losses)| (*player, ships[0..losses].to_vec()))
+            .collect();
+        BattleResult::new(ships_by_player, casualties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::rng::StreamRng;
+    use rand::SeedableRng;
+
+    fn contender(id: usize, ships_count: usize) -> ContenderVessels {
+        let properties = ContenderProperties {
+            id,
+            color: Color::from_rgb(1.0, 1.0, 1.0),
+            bot: true,
+        };
+        let ships = (0..ships_count)
+            .map(|ship_id| VesselProperties {
+                id: id * 100 + ship_id,
+                contender_id: id,
+                celestial_id: 0,
+            })
+            .collect();
+        (properties, ships)
+    }
+
+    fn resolver(model: BattleModel, fighting_factor: f32) -> Box<dyn BattleResolver> {
+        model.resolver(RuleSet {
+            battle_model: model,
+            battle_fighting_factor: fighting_factor,
+            ..RuleSet::default()
+        })
+    }
+
+    #[test]
+    fn lanchester_losses_follow_enemy_count() {
+        let resolver = resolver(BattleModel::Lanchester, 0.25);
+        let ships_by_player = [contender(0, 4), contender(1, 2)];
+        let mut state = BattleState::default();
+        let mut rng = StreamRng::seed_from_u64(0);
+
+        let (mut first_losses, mut second_losses) = (0, 0);
+        for _ in 0..4 {
+            let result = resolver.resolve(&ships_by_player, &mut state, &mut rng);
+            first_losses += result.get_casualties_count(0);
+            second_losses += result.get_casualties_count(1);
+        }
+        assert_eq!(first_losses, 2);
+        assert_eq!(second_losses, 4);
+        assert_eq!(state.damage, vec![(0, 0.0), (1, 0.0)]);
+    }
+
+    #[test]
+    fn lanchester_wipes_out_the_outnumbered_side() {
+        let resolver = resolver(BattleModel::Lanchester, 0.5);
+        let ships_by_player = [contender(0, 10), contender(1, 5)];
+        let mut state = BattleState::default();
+        let mut rng = StreamRng::seed_from_u64(0);
+
+        let result = resolver.resolve(&ships_by_player, &mut state, &mut rng);
+        assert_eq!(result.get_casualties_count(0), 2);
+        assert_eq!(result.get_casualties_count(1), 5);
+        assert_eq!(result.get_casualties().len(), 7);
+        assert_eq!(result.winner.map(|winner| winner.id), Some(0));
+        assert_eq!(state.damage, vec![(0, 0.5), (1, 0.0)]);
+    }
+
+    #[test]
+    fn dice_losses_are_bounded_by_the_compared_dice() {
+        let resolver = resolver(BattleModel::Dice, 1.0);
+        let ships_by_player = [contender(0, 5), contender(1, 2)];
+        let mut state = BattleState::default();
+        let mut rng = StreamRng::seed_from_u64(9);
+
+        let mut total_losses = 0;
+        for _ in 0..200 {
+            let result = resolver.resolve(&ships_by_player, &mut state, &mut rng);
+            let losses = result.get_casualties().len();
+            assert!(losses <= 2);
+            assert!(result.get_casualties_count(1) <= 2);
+            total_losses += losses;
+        }
+        assert!(total_losses > 0);
+    }
+
+    #[test]
+    fn dice_needs_two_sides_to_fight() {
+        let resolver = resolver(BattleModel::Dice, 1.0);
+        let mut state = BattleState::default();
+        let mut rng = StreamRng::seed_from_u64(9);
+
+        let alone = [contender(0, 5), contender(1, 0)];
+        for _ in 0..50 {
+            let result = resolver.resolve(&alone, &mut state, &mut rng);
+            assert!(result.get_casualties().is_empty());
+            assert_eq!(result.winner.map(|winner| winner.id), Some(0));
+        }
+    }
+
+    #[test]
+    fn same_rng_gives_same_casualties() {
+        let ships_by_player = [contender(0, 6), contender(1, 4), contender(2, 3)];
+        for model in [BattleModel::Stochastic, BattleModel::Dice] {
+            let resolver = resolver(model, 0.5);
+            let (mut first_rng, mut second_rng) =
+                (StreamRng::seed_from_u64(3), StreamRng::seed_from_u64(3));
+            for _ in 0..50 {
+                let first = resolver.resolve(
+                    &ships_by_player,
+                    &mut BattleState::default(),
+                    &mut first_rng,
+                );
+                let second = resolver.resolve(
+                    &ships_by_player,
+                    &mut BattleState::default(),
+                    &mut second_rng,
+                );
+                let ids = |result: &BattleResult| -> Vec<usize> {
+                    result.get_casualties().iter().map(|ship| ship.id).collect()
+                };
+                assert_eq!(ids(&first), ids(&second));
+            }
+        }
+    }
+}
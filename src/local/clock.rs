@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimClock {
+    tick_duration: f64,
+    speed: f64,
+    paused: bool,
+    accumulator: f64,
+    ticks: u64,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock::new(SimClock::TICK_DURATION)
+    }
+}
+
+impl SimClock {
+    pub const TICK_DURATION: f64 = 1.0 / 30.0;
+    pub const SPEEDS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+    pub const MAX_TICKS_PER_ADVANCE: u64 = 16;
+
+    pub fn new(tick_duration: f64) -> Self {
+        SimClock {
+            tick_duration,
+            speed: 1.0,
+            paused: false,
+            accumulator: 0.0,
+            ticks: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.paused = false;
+        self.accumulator = 0.0;
+        self.ticks = 0;
+    }
+
+    /// Returns the ticks to run for `delta` seconds of real time. At most
+    /// `MAX_TICKS_PER_ADVANCE` ticks are run at once, the others stay in the
+    /// backlog and are caught up on the next advances.
+    pub fn advance(&mut self, delta: f64) -> u64 {
+        if self.paused {
+            return 0;
+        }
+        self.accumulator += delta * self.speed;
+        let due = (self.accumulator / self.tick_duration).floor() as u64;
+        let ticks = due.min(Self::MAX_TICKS_PER_ADVANCE);
+        self.accumulator -= ticks as f64 * self.tick_duration;
+        ticks
+    }
+
+    pub fn tick(&mut self) -> f64 {
+        self.ticks += 1;
+        self.get_time()
+    }
+
+    pub fn is_every(&self, interval: f64) -> bool {
+        let interval_ticks = ((interval / self.tick_duration).round() as u64).max(1);
+        self.ticks.rem_euclid(interval_ticks) == 0
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.ticks as f64 * self.tick_duration
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn get_tick_duration(&self) -> f64 {
+        self.tick_duration
+    }
+
+    /// Ticks due but not run yet because of the per advance limit.
+    pub fn get_backlog(&self) -> u64 {
+        (self.accumulator / self.tick_duration).floor() as u64
+    }
+
+    pub fn get_alpha(&self) -> f64 {
+        (self.accumulator / self.tick_duration).min(1.0)
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        let min = Self::SPEEDS[0];
+        let max = Self::SPEEDS[Self::SPEEDS.len() - 1];
+        self.speed = speed.clamp(min, max);
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn speed_up(&mut self) {
+        if let Some(speed) = Self::SPEEDS.iter().find(|speed| **speed > self.speed) {
+            self.speed = *speed;
+        }
+    }
+
+    pub fn slow_down(&mut self) {
+        if let Some(speed) = Self::SPEEDS.iter().rev().find(|speed| **speed < self.speed) {
+            self.speed = *speed;
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f64 = 0.25;
+
+    fn run(clock: &mut SimClock, delta: f64) -> u64 {
+        let ticks = clock.advance(delta);
+        (0..ticks).for_each(|_| {
+            clock.tick();
+        });
+        ticks
+    }
+
+    #[test]
+    fn partial_ticks_accumulate() {
+        let mut clock = SimClock::new(TICK);
+        assert_eq!(run(&mut clock, 0.125), 0);
+        assert_eq!(clock.get_alpha(), 0.5);
+        assert_eq!(run(&mut clock, 0.125), 1);
+        assert_eq!(run(&mut clock, 0.5), 2);
+        assert_eq!(clock.get_ticks(), 3);
+        assert_eq!(clock.get_time(), 0.75);
+    }
+
+    #[test]
+    fn backlog_is_caught_up_later() {
+        let mut clock = SimClock::new(TICK);
+        let due = SimClock::MAX_TICKS_PER_ADVANCE + 4;
+        assert_eq!(
+            run(&mut clock, due as f64 * TICK),
+            SimClock::MAX_TICKS_PER_ADVANCE
+        );
+        assert_eq!(clock.get_backlog(), 4);
+        assert_eq!(clock.get_alpha(), 1.0);
+        assert_eq!(run(&mut clock, 0.0), 4);
+        assert_eq!(clock.get_backlog(), 0);
+        assert_eq!(clock.get_ticks(), due);
+    }
+
+    #[test]
+    fn paused_clock_does_not_tick() {
+        let mut clock = SimClock::new(TICK);
+        clock.set_paused(true);
+        assert_eq!(run(&mut clock, 10.0), 0);
+        assert_eq!(clock.get_backlog(), 0);
+        clock.set_paused(false);
+        assert_eq!(run(&mut clock, 1.0), 4);
+    }
+
+    #[test]
+    fn speed_scales_the_ticks() {
+        let mut clock = SimClock::new(TICK);
+        clock.set_speed(2.0);
+        assert_eq!(run(&mut clock, 1.0), 8);
+        clock.slow_down();
+        clock.slow_down();
+        assert_eq!(clock.get_speed(), 0.5);
+        assert_eq!(run(&mut clock, 1.0), 2);
+        clock.slow_down();
+        assert_eq!(clock.get_speed(), 0.5);
+
+        clock.set_speed(100.0);
+        assert_eq!(clock.get_speed(), 4.0);
+        clock.speed_up();
+        assert_eq!(clock.get_speed(), 4.0);
+    }
+
+    #[test]
+    fn is_every_matches_the_interval() {
+        let mut clock = SimClock::new(TICK);
+        let every_second: Vec<bool> = (0..8)
+            .map(|_| {
+                clock.tick();
+                clock.is_every(1.0)
+            })
+            .collect();
+        assert_eq!(
+            every_second,
+            vec![false, false, false, true, false, false, false, true]
+        );
+        assert!(clock.is_every(0.0));
+    }
+
+    #[test]
+    fn reset_keeps_the_speed() {
+        let mut clock = SimClock::new(TICK);
+        clock.set_speed(2.0);
+        clock.set_paused(true);
+        clock.reset();
+        assert_eq!(clock.get_ticks(), 0);
+        assert!(!clock.is_paused());
+        assert_eq!(clock.get_speed(), 2.0);
+    }
+}
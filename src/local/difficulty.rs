@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use super::ai::SearchMode;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// A rollout horizon of 0 scores the moves with the static evaluation only, and
+/// without a sight range the AI sees the ships on every planet.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyProfile {
+    pub exploration: f64,
+    pub playouts: u64,
+    pub budget_factor: f64,
+    pub perception_noise: f32,
+    pub reaction_delay: f64,
+    pub mistake_probability: f64,
+    pub rollout_horizon: f64,
+    pub search_mode: SearchMode,
+    pub verified_transpositions: bool,
+    pub sight_range: Option<f32>,
+}
+
+impl Difficulty {
+    /// Levels are the values of the HUD difficulty slider, from 1 to 3.
+    pub fn from_level(level: usize) -> Self {
+        match level {
+            0 | 1 => Difficulty::Easy,
+            2 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    pub fn get_level(&self) -> usize {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 2,
+            Difficulty::Hard => 3,
+        }
+    }
+
+    pub fn profile(&self) -> DifficultyProfile {
+        match self {
+            Difficulty::Easy => DifficultyProfile {
+                exploration: 0.2,
+                playouts: 128,
+                budget_factor: 0.25,
+                perception_noise: 0.5,
+                reaction_delay: 2.0,
+                mistake_probability: 0.2,
+                rollout_horizon: 0.0,
+                search_mode: SearchMode::Single,
+                verified_transpositions: false,
+                sight_range: None,
+            },
+            Difficulty::Medium => DifficultyProfile {
+                exploration: 0.6,
+                playouts: 512,
+                budget_factor: 0.5,
+                perception_noise: 0.2,
+                reaction_delay: 0.5,
+                mistake_probability: 0.05,
+                rollout_horizon: 0.0,
+                search_mode: SearchMode::Single,
+                verified_transpositions: false,
+                sight_range: None,
+            },
+            Difficulty::Hard => DifficultyProfile {
+                exploration: 1.0,
+                playouts: 1024,
+                budget_factor: 1.0,
+                perception_noise: 0.0,
+                reaction_delay: 0.0,
+                mistake_probability: 0.0,
+                rollout_horizon: 5.0,
+                search_mode: SearchMode::MaxN,
+                verified_transpositions: true,
+                sight_range: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+
+use super::fleet::Fleet;
+use super::model::*;
+use super::rules::ActionError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GameEvent {
+    ShipBuilt(VesselProperties),
+    FleetLaunched(Fleet),
+    FleetArrived(Fleet),
+    ShipDestroyed(VesselProperties),
+    /// The planet, its previous owner (`usize::MAX` when neutral) and its new owner.
+    PlanetCaptured(usize, usize, usize),
+    PlayerEliminated(usize),
+    GameWon(usize),
+    /// The player whose action was rejected, and why, whether a human, an AI or a replay.
+    ActionRejected(usize, ActionError),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EventSubscription(usize);
+
+/// Queues the events of a game for each of its subscribers, which drain them at
+/// their own pace. Events emitted without subscribers are dropped.
+#[derive(Default)]
+pub struct EventBus {
+    queues: Vec<Option<VecDeque<(f64, GameEvent)>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self) -> EventSubscription {
+        match self.queues.iter().position(|queue| queue.is_none()) {
+            Some(index) => {
+                self.queues[index] = Some(VecDeque::new());
+                EventSubscription(index)
+            }
+            None => {
+                self.queues.push(Some(VecDeque::new()));
+                EventSubscription(self.queues.len() - 1)
+            }
+        }
+    }
+
+    pub fn unsubscribe(&mut self, subscription: EventSubscription) {
+        if let Some(queue) = self.queues.get_mut(subscription.0) {
+            *queue = None;
+        }
+    }
+
+    pub fn emit(&mut self, time: f64, event: GameEvent) {
+        for queue in self.queues.iter_mut().flatten() {
+            queue.push_back((time, event.clone()));
+        }
+    }
+
+    /// Returns the events emitted since the last drain of the subscription, with
+    /// the game time they happened at.
+    pub fn drain(&mut self, subscription: EventSubscription) -> Vec<(f64, GameEvent)> {
+        match self.queues.get_mut(subscription.0) {
+            Some(Some(queue)) => queue.drain(..).collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for queue in self.queues.iter_mut().flatten() {
+            queue.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn won(events: Vec<(f64, GameEvent)>) -> Vec<(f64, usize)> {
+        events
+            .into_iter()
+            .map(|(time, event)| match event {
+                GameEvent::GameWon(player_id) => (time, player_id),
+                event => panic!("unexpected event {:?}", event),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_are_drained_once_in_order() {
+        let mut bus = EventBus::default();
+        let subscription = bus.subscribe();
+        bus.emit(1.0, GameEvent::GameWon(0));
+        bus.emit(2.0, GameEvent::GameWon(1));
+
+        assert_eq!(won(bus.drain(subscription)), vec![(1.0, 0), (2.0, 1)]);
+        assert!(bus.drain(subscription).is_empty());
+    }
+
+    #[test]
+    fn subscribers_drain_their_own_queue() {
+        let mut bus = EventBus::default();
+        let first = bus.subscribe();
+        bus.emit(1.0, GameEvent::GameWon(0));
+        let second = bus.subscribe();
+        bus.emit(2.0, GameEvent::GameWon(1));
+
+        assert_eq!(won(bus.drain(first)), vec![(1.0, 0), (2.0, 1)]);
+        assert_eq!(won(bus.drain(second)), vec![(2.0, 1)]);
+    }
+
+    #[test]
+    fn unsubscribed_queues_are_dropped_and_reused() {
+        let mut bus = EventBus::default();
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+        bus.emit(1.0, GameEvent::GameWon(0));
+        bus.unsubscribe(first);
+
+        assert!(bus.drain(first).is_empty());
+        assert_eq!(won(bus.drain(second)), vec![(1.0, 0)]);
+
+        let third = bus.subscribe();
+        assert_eq!(third, first);
+        bus.emit(2.0, GameEvent::GameWon(1));
+        assert_eq!(won(bus.drain(third)), vec![(2.0, 1)]);
+    }
+
+    #[test]
+    fn events_without_subscribers_are_dropped() {
+        let mut bus = EventBus::default();
+        bus.emit(1.0, GameEvent::GameWon(0));
+        let subscription = bus.subscribe();
+        assert!(bus.drain(subscription).is_empty());
+
+        bus.emit(2.0, GameEvent::GameWon(1));
+        bus.clear();
+        assert!(bus.drain(subscription).is_empty());
+    }
+}
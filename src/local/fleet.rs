@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use super::model::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fleet {
+    pub id: usize,
+    pub contender_id: usize,
+    pub origin_id: usize,
+    pub destination_id: usize,
+    pub ships: Vec<VesselProperties>,
+    pub distance: f32,
+    pub departure_time: f64,
+    pub arrival_time: f64,
+}
+
+impl Fleet {
+    pub fn eta(&self, time: f64) -> f64 {
+        (self.arrival_time - time).max(0.0)
+    }
+
+    pub fn progress(&self, time: f64) -> f32 {
+        let duration = self.arrival_time - self.departure_time;
+        if duration <= 0.0 {
+            return 1.0;
+        }
+        ((time - self.departure_time) / duration).clamp(0.0, 1.0) as f32
+    }
+
+    pub fn has_arrived(&self, time: f64) -> bool {
+        time >= self.arrival_time
+    }
+}
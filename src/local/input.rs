@@ -1,7 +1,24 @@
-use gdnative::prelude::*;
-
 use super::player::PlayerAction;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+        shift: bool,
+        control: bool,
+    },
+}
+
 pub trait InputHandler<T> {
-    fn convert(&mut self, target: T, event: Ref<InputEvent>) -> PlayerAction;
+    fn convert(&mut self, target: T, event: InputEvent) -> PlayerAction;
 }
@@ -1,24 +1,46 @@
 pub mod ai;
+pub mod battle;
+pub mod clock;
+pub mod difficulty;
+pub mod event;
+pub mod fleet;
 pub mod input;
 pub mod model;
 pub mod planet;
 pub mod player;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod ruleset;
+pub mod sim;
+pub mod snapshot;
 pub mod starmap;
+pub mod stats;
+pub mod tournament;
 
+use rand::Rng;
+
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 
+use self::clock::SimClock;
+use self::difficulty::Difficulty;
+use self::event::*;
+use self::fleet::Fleet;
 use self::player::*;
+use self::replay::*;
+use self::rng::*;
+use self::ruleset::RuleSet;
+use self::snapshot::*;
 use self::starmap::Starmap;
+use crate::local::ai::personality::AiPersonality;
+use crate::local::ai::pool::AiWorkerPool;
+use crate::local::ai::scheduler::AiScheduler;
 use crate::local::ai::*;
 use crate::local::model::*;
-use crate::local::Difficulty::Medium;
 
-#[derive(Debug, Copy, Clone)]
-pub enum Difficulty {
-    Easy,
-    Medium,
-    Hard,
-}
+pub const EXTRACTION_INTERVAL: f64 = 1.0;
 
 pub struct GameState<T, U>
 where
@@ -28,8 +50,23 @@ where
     starmap: Option<T>,
     players: Vec<Rc<U>>,
     ais: Vec<AiState>,
+    ai_pool: Option<AiWorkerPool>,
+    ai_scheduler: AiScheduler,
     difficulty: Difficulty,
+    ai_difficulties: Vec<Difficulty>,
+    ai_personalities: Vec<AiPersonality>,
     time: f64,
+    clock: SimClock,
+    rng: GameRng,
+    rules: RuleSet,
+    fleets: RefCell<Vec<Fleet>>,
+    fleet_ids: Cell<usize>,
+    vessel_ids: Cell<usize>,
+    recorder: RefCell<Option<ReplayRecorder>>,
+    replayer: Option<Replayer>,
+    events: RefCell<EventBus>,
+    eliminated_ids: RefCell<Vec<usize>>,
+    winner_id: Cell<Option<usize>>,
 }
 
 impl<T, U> Default for GameState<T, U>
@@ -42,8 +79,23 @@ where
             starmap: None,
             players: vec![],
             ais: vec![],
-            difficulty: Medium,
+            ai_pool: None,
+            ai_scheduler: AiScheduler::default(),
+            difficulty: Difficulty::Medium,
+            ai_difficulties: vec![],
+            ai_personalities: vec![],
             time: 0.0,
+            clock: SimClock::default(),
+            rng: GameRng::default(),
+            rules: RuleSet::default(),
+            fleets: RefCell::new(vec![]),
+            fleet_ids: Cell::new(0),
+            vessel_ids: Cell::new(0),
+            recorder: RefCell::new(None),
+            replayer: None,
+            events: RefCell::new(EventBus::default()),
+            eliminated_ids: RefCell::new(vec![]),
+            winner_id: Cell::new(None),
         }
     }
 }
@@ -61,14 +113,273 @@ where
         self.players.iter().for_each(|p| p.destroy());
         self.players.clear();
         self.ais.clear();
-        self.difficulty = Medium;
+        if let Some(ai_pool) = &mut self.ai_pool {
+            ai_pool.cancel();
+        }
         if let Some(starmap) = &mut self.starmap {
             starmap.destroy();
         }
         self.time = 0.0;
+        self.clock.reset();
+        self.rng = GameRng::new(self.rng.get_seed());
+        self.fleets.borrow_mut().clear();
+        self.fleet_ids.set(0);
+        self.vessel_ids.set(0);
+        self.recorder.replace(None);
+        self.replayer = None;
+        self.events.borrow_mut().clear();
+        self.eliminated_ids.borrow_mut().clear();
+        self.winner_id.set(None);
+    }
+
+    pub fn snapshot(&self, setup: GameSetup) -> GameSnapshot {
+        GameSnapshot {
+            version: SNAPSHOT_VERSION,
+            setup,
+            clock: self.clock,
+            rng: self.rng.snapshot(),
+            planets: self.get_starmap().snapshot(),
+            players: self
+                .players
+                .iter()
+                .map(|player| player.snapshot())
+                .collect(),
+            ais: self.ais.iter().map(|ai| ai.snapshot()).collect(),
+            fleets: self.fleets.borrow().clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &GameSnapshot, players: Vec<Rc<U>>) {
+        self.players = players;
+        self.rules = snapshot.setup.rules;
+        self.difficulty = snapshot.setup.get_difficulty();
+        self.ai_difficulties = snapshot.setup.get_ai_difficulties();
+        self.ai_personalities = snapshot.setup.ai_personalities.clone();
+        self.ais = snapshot
+            .ais
+            .iter()
+            .map(|ai| AiState::restore(ai, self.rules))
+            .collect();
+        if let Some(ai_pool) = &mut self.ai_pool {
+            ai_pool.cancel();
+        }
+        self.clock = snapshot.clock;
+        self.time = self.clock.get_time();
+        self.rng = GameRng::restore(&snapshot.rng);
+        self.fleets.replace(snapshot.fleets.clone());
+        self.fleet_ids.set(
+            snapshot
+                .fleets
+                .iter()
+                .map(|fleet| fleet.id + 1)
+                .max()
+                .unwrap_or(0),
+        );
+        self.vessel_ids.set(
+            snapshot
+                .players
+                .iter()
+                .flat_map(|player| player.ships.iter())
+                .map(|ship| ship.id + 1)
+                .max()
+                .unwrap_or(0),
+        );
+        self.events.borrow_mut().clear();
+        let (winner, not_playing) = self.check_game_over();
+        self.eliminated_ids.replace(
+            not_playing
+                .iter()
+                .map(|player| player.properties().id)
+                .collect(),
+        );
+        self.winner_id
+            .set(winner.map(|winner| winner.properties().id));
+    }
+
+    pub fn next_vessel_id(&self) -> usize {
+        let id = self.vessel_ids.get();
+        self.vessel_ids.set(id + 1);
+        id
+    }
+
+    pub fn launch_fleet(
+        &self,
+        contender_id: usize,
+        origin_id: usize,
+        destination_id: usize,
+        ships: Vec<VesselProperties>,
+    ) {
+        if ships.is_empty() {
+            return;
+        }
+        let starmap = self.get_starmap();
+        let planets = starmap.get_planets();
+        let destination = starmap.get_planet_properties(destination_id);
+        let distance = (T::get_distance_between(&planets[origin_id], &planets[destination_id])
+            - destination.radius)
+            .max(0.0);
+        let speed = self.rules.fleet_speed();
+
+        let id = self.fleet_ids.get();
+        self.fleet_ids.set(id + 1);
+        let fleet = Fleet {
+            id,
+            contender_id,
+            origin_id,
+            destination_id,
+            ships,
+            distance,
+            departure_time: self.time,
+            arrival_time: self.time + (distance / speed) as f64,
+        };
+        self.emit_event(GameEvent::FleetLaunched(fleet.clone()));
+        self.fleets.borrow_mut().push(fleet);
+    }
+
+    pub fn update_fleets(&self) -> Vec<Fleet> {
+        let mut fleets = self.fleets.borrow_mut();
+        let (mut arrived, travelling): (Vec<Fleet>, Vec<Fleet>) = fleets
+            .drain(..)
+            .partition(|fleet| fleet.has_arrived(self.time));
+        *fleets = travelling;
+        arrived.sort_by(|fleet1, fleet2| {
+            fleet1
+                .arrival_time
+                .total_cmp(&fleet2.arrival_time)
+                .then(fleet1.id.cmp(&fleet2.id))
+        });
+        for fleet in arrived.iter() {
+            self.emit_event(GameEvent::FleetArrived(fleet.clone()));
+        }
+        arrived
+    }
+
+    pub fn get_fleets(&self) -> Vec<Fleet> {
+        self.fleets.borrow().clone()
+    }
+
+    pub fn get_incoming_fleets(&self, planet_id: usize) -> Vec<Fleet> {
+        self.fleets
+            .borrow()
+            .iter()
+            .filter(|fleet| fleet.destination_id == planet_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn subscribe_events(&self) -> EventSubscription {
+        self.events.borrow_mut().subscribe()
+    }
+
+    pub fn unsubscribe_events(&self, subscription: EventSubscription) {
+        self.events.borrow_mut().unsubscribe(subscription);
+    }
+
+    pub fn poll_events(&self, subscription: EventSubscription) -> Vec<(f64, GameEvent)> {
+        self.events.borrow_mut().drain(subscription)
+    }
+
+    pub fn emit_event(&self, event: GameEvent) {
+        self.events.borrow_mut().emit(self.time, event);
+    }
+
+    /// Emits the eliminations of the players who lost their last ship since the
+    /// previous update, then the victory of the last player standing.
+    pub fn update_standings(&self) {
+        if self.winner_id.get().is_some() {
+            return;
+        }
+        let (winner, not_playing) = self.check_game_over();
+        for player in not_playing {
+            let player_id = player.properties().id;
+            let mut eliminated_ids = self.eliminated_ids.borrow_mut();
+            if !eliminated_ids.contains(&player_id) {
+                eliminated_ids.push(player_id);
+                self.emit_event(GameEvent::PlayerEliminated(player_id));
+            }
+        }
+        if let Some(winner) = winner {
+            let winner_id = winner.properties().id;
+            self.winner_id.set(Some(winner_id));
+            self.emit_event(GameEvent::GameWon(winner_id));
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = GameRng::new(seed);
+    }
+
+    pub fn get_rng(&self) -> &GameRng {
+        &self.rng
+    }
+
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+    }
+
+    /// Overrides the difficulty of the AIs in the order they join the game, so that
+    /// a lobby may mix them; the AIs beyond the list play at the game difficulty.
+    pub fn set_ai_difficulties(&mut self, ai_difficulties: Vec<Difficulty>) {
+        self.ai_difficulties = ai_difficulties;
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Sets the personality of the AIs in the order they join the game; the AIs
+    /// beyond the list get a random one.
+    pub fn set_ai_personalities(&mut self, ai_personalities: Vec<AiPersonality>) {
+        self.ai_personalities = ai_personalities;
+    }
+
+    pub fn get_rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn start_recording(&mut self, setup: GameSetup) {
+        self.recorder.replace(Some(ReplayRecorder::new(setup)));
+    }
+
+    pub fn record_action(&self, contender_id: usize, player_action: PlayerAction) {
+        if let Some(recorder) = self.recorder.borrow_mut().as_mut() {
+            recorder.record(self.time, contender_id, player_action);
+        }
+    }
+
+    pub fn get_replay(&self) -> Option<Replay> {
+        self.recorder
+            .borrow()
+            .as_ref()
+            .map(|recorder| recorder.get_replay().clone())
+    }
+
+    pub fn set_replayer(&mut self, replayer: Replayer) {
+        self.replayer = Some(replayer);
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replayer.is_some()
+    }
+
+    pub fn update_replay(&mut self) -> Vec<(usize, PlayerAction)> {
+        let time = self.time;
+        match self.replayer.as_mut() {
+            Some(replayer) => replayer
+                .due_actions(time)
+                .iter()
+                .map(|entry| (entry.contender_id, entry.action))
+                .collect(),
+            None => vec![],
+        }
     }
 
     pub fn set_starmap(&mut self, starmap: T) {
+        self.ai_scheduler.cache_planet_distances(&starmap);
         self.starmap = Some(starmap);
     }
 
@@ -76,12 +387,52 @@ where
         self.players.push(player.clone());
         let player_props = player.properties();
         if player_props.bot {
-            self.ais.push(AiState::new(player_props, self.difficulty));
+            let seed = self.rng.with(RngStream::Ai, |rng| rng.gen());
+            let difficulty = self
+                .ai_difficulties
+                .get(self.ais.len())
+                .copied()
+                .unwrap_or(self.difficulty);
+            let personality = match self.ai_personalities.get(self.ais.len()) {
+                Some(personality) => *personality,
+                None => self.rng.with(RngStream::Ai, AiPersonality::random),
+            };
+            let mut ai = AiState::new(player_props, difficulty, personality, seed, self.rules);
+            AiScheduler::stagger(&mut ai, self.ais.len(), self.time);
+            self.ais.push(ai);
         }
     }
 
-    pub fn add_time_delta(&mut self, delta: f64) {
-        self.time += delta;
+    pub fn add_time_delta(&mut self, delta: f64) -> u64 {
+        self.clock.advance(delta)
+    }
+
+    pub fn next_tick(&mut self) {
+        self.time = self.clock.tick();
+    }
+
+    pub fn is_extraction_tick(&self) -> bool {
+        self.clock.is_every(EXTRACTION_INTERVAL)
+    }
+
+    pub fn get_clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    pub fn get_clock_mut(&mut self) -> &mut SimClock {
+        &mut self.clock
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn get_ais(&self) -> &[AiState] {
+        &self.ais
+    }
+
+    pub fn get_ais_mut(&mut self) -> &mut [AiState] {
+        &mut self.ais
     }
 
     pub fn get_current_player(&self) -> Option<&Rc<U>> {
@@ -111,42 +462,81 @@ where
 
     pub fn update_ai(&mut self) -> Vec<(ContenderProperties, PlayerAction)> {
         let mut ai_moves = vec![];
-        let mut ships_by_player_by_planet = vec![];
-        let starmap = self.starmap.as_ref().unwrap();
-        let planets = starmap.get_planets();
-        let mut planet_distances = vec![];
-        planets
+        if let Some(ai_pool) = &mut self.ai_pool {
+            for (ai_result, ai_move) in ai_pool.poll() {
+                let player = ai_result.get_player();
+                if let Some(ai) = self
+                    .ais
+                    .iter_mut()
+                    .find(|ai| ai.get_player().id == player.id)
+                {
+                    *ai = ai_result;
+                    ai_moves.push((player, ai_move));
+                }
+            }
+        }
+
+        let time = self.time;
+        let fleets = self.fleets.borrow();
+        let due_ais: Vec<usize> = self
+            .ais
             .iter()
             .enumerate()
-            .for_each(|(planet_id, planet_node)| {
-                let mut distances = vec![];
-                planets.iter().for_each(|pn| {
-                    let dist = T::get_distance_between(planet_node, pn);
-                    distances.push(dist);
-                });
-                planet_distances.push(distances);
+            .filter(|(_, ai)| {
+                let is_pending = match &self.ai_pool {
+                    Some(ai_pool) => ai_pool.is_pending(ai.get_player().id),
+                    None => false,
+                };
+                !is_pending && AiScheduler::is_due(ai, time, &fleets)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if due_ais.is_empty() {
+            return ai_moves;
+        }
+
+        let starmap = self.starmap.as_ref().unwrap();
+        let ships_by_player_by_planet: Vec<_> = (0..starmap.get_planets().len())
+            .map(|planet_id| {
                 let planet_props = starmap.get_planet_properties(planet_id);
-                let ships_by_player = self.get_ships_by_player_on_planet(planet_props);
-                ships_by_player_by_planet.push((planet_props, ships_by_player));
-            });
+                (
+                    planet_props,
+                    self.get_ships_by_player_on_planet(planet_props),
+                )
+            })
+            .collect();
         let player_properties: Vec<ContenderProperties> = self
             .players
             .iter()
             .map(|player| player.properties())
             .collect();
-        self.ais.iter_mut().for_each(|ai| {
+        let planet_distances = self.ai_scheduler.get_planet_distances();
+        for index in due_ais {
+            let ai = &mut self.ais[index];
             ai.refresh_measures(
-                &planet_distances,
+                planet_distances,
                 &player_properties,
                 ships_by_player_by_planet.to_vec(),
+                &fleets,
             );
-            let tuple = (ai.get_player(), ai.get_best_move());
-            ai_moves.push(tuple);
-        });
+            AiScheduler::schedule(ai, time);
+            match &mut self.ai_pool {
+                Some(ai_pool) => ai_pool.submit(ai.detach()),
+                None => ai_moves.push((ai.get_player(), ai.get_best_move())),
+            }
+        }
 
         ai_moves
     }
 
+    pub fn start_ai_workers(&mut self, workers_count: usize, budget: Duration) {
+        self.ai_pool = AiWorkerPool::new(workers_count, budget);
+    }
+
+    pub fn has_ai_workers(&self) -> bool {
+        self.ai_pool.is_some()
+    }
+
     pub fn check_game_over(&self) -> (Option<Rc<U>>, Vec<Rc<U>>) {
         let playing: Vec<Rc<U>> = self
             .players
@@ -171,7 +561,7 @@ where
             })
             .collect();
         if playing.len() == 1 {
-            return (Some(playing.get(0).unwrap().clone()), not_playing);
+            return (Some(playing.first().unwrap().clone()), not_playing);
         }
         (None, not_playing)
     }
@@ -1,18 +1,20 @@
-use gdnative::prelude::*;
-
-pub struct Consts {}
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
 
-impl Consts {
-    pub const ADD_PLAYER_RESOURCES_INIT: f32 = 200.0;
-    pub const ADD_PLAYER_RESOURCES_INC: f32 = 0.0018;
-    pub const PLANET_RESOURCES_INIT: f32 = 600.0;
-    pub const PLANET_RESOURCES_INC: f32 = 0.0012;
-    pub const ADD_SHIP_RESOURCE_COST: f32 = 10.0;
-    pub const MOVE_SHIP_FLEET_PERCENT: usize = 50;
-    pub const MOVE_SHIP_SPEED_MULT: f32 = 10.0;
+impl Color {
+    pub fn from_rgb(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct ContenderProperties {
     pub id: usize,
     pub color: Color,
@@ -23,7 +25,7 @@ pub trait Contender {
     fn properties(&self) -> ContenderProperties;
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct VesselProperties {
     pub id: usize,
     pub contender_id: usize,
@@ -34,7 +36,7 @@ pub trait Vessel {
     fn properties(&self) -> VesselProperties;
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct CelestialProperties {
     pub id: usize,
     pub contender_id: usize,
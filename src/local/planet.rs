@@ -1,13 +1,29 @@
 use rand::*;
 
+use std::cell::RefCell;
+
+use super::battle::*;
 use super::model::*;
+use super::player::FleetSize;
+use super::ruleset::RuleSet;
+
+pub struct PlanetBusiness {
+    resolver: Box<dyn BattleResolver>,
+    battle_state: RefCell<BattleState>,
+}
 
-#[derive(Default)]
-pub struct PlanetBusiness {}
+impl Default for PlanetBusiness {
+    fn default() -> Self {
+        PlanetBusiness::new(RuleSet::default())
+    }
+}
 
 impl PlanetBusiness {
-    pub fn new() -> Self {
-        PlanetBusiness::default()
+    pub fn new(rules: RuleSet) -> Self {
+        PlanetBusiness {
+            resolver: rules.battle_model.resolver(rules),
+            battle_state: RefCell::new(BattleState::default()),
+        }
     }
 
     pub fn resources_update(&self, props: &mut CelestialProperties, orbiters_count: i32) {
@@ -36,59 +52,30 @@ impl PlanetBusiness {
         false
     }
 
-    pub fn count_ships_to_move(&self, ships_count: usize, percent: usize) -> usize {
-        (ships_count as f32 * percent as f32 / 100.0).floor() as usize
+    pub fn count_ships_to_move(&self, ships_count: usize, size: FleetSize) -> usize {
+        match size {
+            FleetSize::Percent(percent) => {
+                (ships_count as f32 * percent.min(100) as f32 / 100.0).floor() as usize
+            }
+            FleetSize::Count(count) => count.min(ships_count),
+            FleetSize::AllBut(kept) => ships_count.saturating_sub(kept),
+        }
     }
 
-    pub fn battle(
+    pub fn battle<R: Rng>(
         &self,
-        ships_by_player: Vec<(ContenderProperties, Vec<VesselProperties>)>,
-    ) -> (Option<ContenderProperties>, Vec<VesselProperties>) {
-        let total_ship_count: usize = ships_by_player.iter().map(|(_, ships)| ships.len()).sum();
-        let ship_loss_probs: Vec<f32> = ships_by_player
-            .iter()
-            .map(|(_, ships)| {
-                if total_ship_count == 0 {
-                    return 0.0;
-                }
-                let fighting_time_factor = 0.005 * (1.0 - (-(total_ship_count as f32).ln()).exp());
-                1.0 - (-((total_ship_count - ships.len()) as f32 * fighting_time_factor + 1.0).ln())
-                    .exp()
-            })
-            .collect();
+        ships_by_player: Vec<ContenderVessels>,
+        rng: &mut R,
+    ) -> BattleResult {
+        self.resolver
+            .resolve(&ships_by_player, &mut self.battle_state.borrow_mut(), rng)
+    }
 
-        let mut casualties = vec![];
-        let mut rng = rand::thread_rng();
-        ship_loss_probs
-            .iter()
-            .enumerate()
-            .for_each(|(index, prob)| {
-                let dice = rng.gen_range(0.0..1.0);
-                let ships = &ships_by_player.get(index).unwrap().1;
-                if dice > 1.0 - *prob && !ships.is_empty() {
-                    let rnd_index = rng.gen_range(0.0..(ships.len() as f32)).floor() as usize;
-                    let ship_props = *ships.get(rnd_index).unwrap();
-                    casualties.push(ship_props);
-                }
-            });
+    pub fn get_battle_state(&self) -> BattleState {
+        self.battle_state.borrow().clone()
+    }
 
-        let mut winner = None;
-        let remaining_players: Vec<ContenderProperties> = ships_by_player
-            .iter()
-            .filter_map(|(player, ships)| {
-                let player_casualties = casualties
-                    .iter()
-                    .filter(|c| c.contender_id == player.id)
-                    .count();
-                if ships.len().saturating_sub(player_casualties) > 0 {
-                    return Some(*player);
-                }
-                None
-            })
-            .collect();
-        if remaining_players.len() == 1 {
-            winner = Some(*remaining_players.get(0).unwrap());
-        }
-        (winner, casualties)
+    pub fn set_battle_state(&self, battle_state: BattleState) {
+        self.battle_state.replace(battle_state);
     }
 }
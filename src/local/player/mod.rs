@@ -1,12 +1,40 @@
+use serde::{Deserialize, Serialize};
+
 use super::model::*;
+use super::snapshot::PlayerSnapshot;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FleetSize {
+    Percent(usize),
+    Count(usize),
+    AllBut(usize),
+}
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerAction {
     AddShip(CelestialProperties),
-    MoveShips(CelestialProperties, CelestialProperties),
+    MoveShips(CelestialProperties, CelestialProperties, FleetSize),
     Wait,
 }
 
+/// Actions are equal when they involve the same planets, whatever their extracted
+/// resources at the time the action was chosen.
+impl PartialEq for PlayerAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PlayerAction::AddShip(on), PlayerAction::AddShip(other_on)) => on.id == other_on.id,
+            (
+                PlayerAction::MoveShips(from, to, size),
+                PlayerAction::MoveShips(other_from, other_to, other_size),
+            ) => from.id == other_from.id && to.id == other_to.id && size == other_size,
+            (PlayerAction::Wait, PlayerAction::Wait) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PlayerAction {}
+
 pub trait Player: Contender {
     type CelestialType;
     type VesselType;
@@ -16,4 +44,38 @@ pub trait Player: Contender {
     fn add_ship(&self, ship: Self::VesselType);
     fn is_playing(&self) -> bool;
     fn get_ships_on_planet(&self, planet: CelestialProperties) -> Vec<VesselProperties>;
+    fn snapshot(&self) -> PlayerSnapshot;
+}
+
+/// Number of players that can tell their colors apart.
+pub const MAX_PLAYERS: usize = 20;
+
+pub fn get_color(id: usize) -> Color {
+    let colors: [(u8, u8, u8); MAX_PLAYERS] = [
+        (230, 25, 75),   // Red
+        (60, 180, 75),   // Green
+        (255, 225, 25),  // Yellow
+        (0, 130, 200),   // Blue
+        (245, 130, 48),  // Orange
+        (145, 30, 180),  // Purple
+        (70, 240, 240),  // Cyan
+        (240, 50, 230),  // Magenta
+        (210, 245, 60),  // Lime
+        (250, 190, 212), // Pink
+        (0, 128, 128),   // Teal
+        (220, 190, 255), // Lavender
+        (170, 110, 40),  // Brown
+        (255, 250, 200), // Beige
+        (128, 0, 0),     // Maroon
+        (170, 255, 195), // Mint
+        (128, 128, 0),   // Olive
+        (255, 215, 180), // Apricot
+        (0, 0, 128),     // Navy
+        (128, 128, 128), // Grey
+    ];
+    Color::from_rgb(
+        colors[id].0 as f32 / 255.0,
+        colors[id].1 as f32 / 255.0,
+        colors[id].2 as f32 / 255.0,
+    )
 }
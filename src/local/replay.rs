@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+use super::ai::personality::AiPersonality;
+use super::difficulty::Difficulty;
+use super::player::PlayerAction;
+use super::ruleset::RuleSet;
+use super::starmap::layout::StarmapLayout;
+
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    Io(String),
+    Format(String),
+    Version(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub seed: u64,
+    pub players_count: usize,
+    pub planets_count: usize,
+    pub difficulty: usize,
+    #[serde(default)]
+    pub ai_difficulties: Vec<usize>,
+    #[serde(default)]
+    pub ai_personalities: Vec<AiPersonality>,
+    pub demo: bool,
+    pub rules: RuleSet,
+    #[serde(default)]
+    pub layout: StarmapLayout,
+}
+
+impl GameSetup {
+    pub fn get_difficulty(&self) -> Difficulty {
+        Difficulty::from_level(self.difficulty)
+    }
+
+    pub fn get_ai_difficulties(&self) -> Vec<Difficulty> {
+        self.ai_difficulties
+            .iter()
+            .map(|level| Difficulty::from_level(*level))
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    pub time: f64,
+    pub contender_id: usize,
+    pub action: PlayerAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub setup: GameSetup,
+    pub actions: Vec<ReplayEntry>,
+}
+
+impl Replay {
+    pub fn new(setup: GameSetup) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            setup,
+            actions: vec![],
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, ReplayError> {
+        serde_json::to_string(self).map_err(|e| ReplayError::Format(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let replay: Replay =
+            serde_json::from_str(json).map_err(|e| ReplayError::Format(e.to_string()))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        fs::write(path, self.to_json()?).map_err(|e| ReplayError::Io(e.to_string()))
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        let json = fs::read_to_string(path).map_err(|e| ReplayError::Io(e.to_string()))?;
+        Replay::from_json(&json)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayRecorder {
+    replay: Replay,
+}
+
+impl ReplayRecorder {
+    pub fn new(setup: GameSetup) -> Self {
+        ReplayRecorder {
+            replay: Replay::new(setup),
+        }
+    }
+
+    pub fn record(&mut self, time: f64, contender_id: usize, action: PlayerAction) {
+        if let PlayerAction::Wait = action {
+            return;
+        }
+        self.replay.actions.push(ReplayEntry {
+            time,
+            contender_id,
+            action,
+        });
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    replay: Replay,
+    cursor: usize,
+}
+
+impl Replayer {
+    pub fn new(replay: Replay) -> Self {
+        Replayer { replay, cursor: 0 }
+    }
+
+    pub fn get_setup(&self) -> GameSetup {
+        self.replay.setup.clone()
+    }
+
+    pub fn due_actions(&mut self, time: f64) -> Vec<ReplayEntry> {
+        let due = self.replay.actions[self.cursor..]
+            .iter()
+            .take_while(|entry| entry.time <= time)
+            .count();
+        let entries = self.replay.actions[self.cursor..self.cursor + due].to_vec();
+        self.cursor += due;
+        entries
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.actions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::clock::SimClock;
+    use crate::local::model::*;
+    use crate::local::sim::SimGame;
+    use crate::local::starmap::Starmap;
+
+    #[test]
+    fn replay_reaches_the_recorded_final_state() {
+        let mut game = SimGame::demo(2, 3, 3);
+        let winner = game.run(SimClock::TICK_DURATION, 600.0);
+        let json = game.get_replay().unwrap().to_json().unwrap();
+
+        let mut replayed = SimGame::replay(Replay::from_json(&json).unwrap());
+        let replayed_winner = replayed.run(SimClock::TICK_DURATION, 600.0);
+
+        assert_eq!(
+            winner.map(|player| player.properties().id),
+            replayed_winner.map(|player| player.properties().id)
+        );
+        let (recorded, replayed) = (game.snapshot(), replayed.snapshot());
+        assert_eq!(recorded.clock.get_ticks(), replayed.clock.get_ticks());
+        assert_eq!(
+            serde_json::to_string(&recorded.planets).unwrap(),
+            serde_json::to_string(&replayed.planets).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&recorded.players).unwrap(),
+            serde_json::to_string(&replayed.players).unwrap()
+        );
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut replay = SimGame::demo(2, 3, 3).get_replay().unwrap();
+        replay.version = REPLAY_VERSION + 1;
+        let json = replay.to_json().unwrap();
+        assert_eq!(
+            Replay::from_json(&json).unwrap_err(),
+            ReplayError::Version(REPLAY_VERSION + 1)
+        );
+
+        let legacy = json.replacen(
+            &format!("\"version\":{}", REPLAY_VERSION + 1),
+            "\"version\":0",
+            1,
+        );
+        assert_eq!(
+            Replay::from_json(&legacy).unwrap_err(),
+            ReplayError::Version(0)
+        );
+    }
+
+    #[test]
+    fn replayer_returns_each_action_once() {
+        let game = SimGame::demo(2, 3, 3);
+        let on = game.get_game_state().get_starmap().get_planet_properties(0);
+        let mut recorder = ReplayRecorder::new(game.get_setup());
+        recorder.record(1.0, 0, PlayerAction::AddShip(on));
+        recorder.record(1.5, 0, PlayerAction::Wait);
+        recorder.record(2.0, 1, PlayerAction::AddShip(on));
+
+        let mut replayer = Replayer::new(recorder.get_replay().clone());
+        assert_eq!(replayer.due_actions(0.5).len(), 0);
+        assert_eq!(replayer.due_actions(1.5).len(), 1);
+        assert!(!replayer.is_finished());
+        assert_eq!(replayer.due_actions(3.0).len(), 1);
+        assert!(replayer.is_finished());
+    }
+}
@@ -0,0 +1,146 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use std::cell::RefCell;
+
+pub type StreamRng = ChaCha12Rng;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RngStream {
+    Starmap,
+    Battle,
+    Orbit,
+    Background,
+    Ai,
+}
+
+impl RngStream {
+    const ALL: [RngStream; 5] = [
+        RngStream::Starmap,
+        RngStream::Battle,
+        RngStream::Orbit,
+        RngStream::Background,
+        RngStream::Ai,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRngSnapshot {
+    pub seed: u64,
+    pub word_positions: Vec<u128>,
+}
+
+/// One seed for the whole game, split in independent streams so that a subsystem
+/// drawing more or less numbers does not shift the draws of the others.
+#[derive(Debug)]
+pub struct GameRng {
+    seed: u64,
+    streams: RefCell<Vec<StreamRng>>,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let streams = RngStream::ALL
+            .iter()
+            .map(|stream| StreamRng::seed_from_u64(Self::stream_seed(seed, *stream)))
+            .collect();
+        GameRng {
+            seed,
+            streams: RefCell::new(streams),
+        }
+    }
+
+    pub fn random_seed() -> u64 {
+        rand::random()
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn snapshot(&self) -> GameRngSnapshot {
+        GameRngSnapshot {
+            seed: self.seed,
+            word_positions: self
+                .streams
+                .borrow()
+                .iter()
+                .map(|stream| stream.get_word_pos())
+                .collect(),
+        }
+    }
+
+    pub fn restore(snapshot: &GameRngSnapshot) -> Self {
+        let rng = GameRng::new(snapshot.seed);
+        rng.streams
+            .borrow_mut()
+            .iter_mut()
+            .zip(snapshot.word_positions.iter())
+            .for_each(|(stream, word_pos)| stream.set_word_pos(*word_pos));
+        rng
+    }
+
+    pub fn with<F, T>(&self, stream: RngStream, with_fn: F) -> T
+    where
+        F: FnOnce(&mut StreamRng) -> T,
+    {
+        let mut streams = self.streams.borrow_mut();
+        with_fn(&mut streams[stream as usize])
+    }
+
+    fn stream_seed(seed: u64, stream: RngStream) -> u64 {
+        seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn draw(rng: &GameRng, stream: RngStream) -> Vec<u64> {
+        rng.with(stream, |rng| (0..8).map(|_| rng.gen()).collect())
+    }
+
+    #[test]
+    fn same_seed_gives_same_draws() {
+        let first = GameRng::new(42);
+        let second = GameRng::new(42);
+        for stream in RngStream::ALL {
+            assert_eq!(draw(&first, stream), draw(&second, stream));
+        }
+        assert_ne!(
+            draw(&GameRng::new(42), RngStream::Battle),
+            draw(&GameRng::new(43), RngStream::Battle)
+        );
+    }
+
+    #[test]
+    fn streams_do_not_shift_each_other() {
+        let quiet = GameRng::new(7);
+        let busy = GameRng::new(7);
+        draw(&busy, RngStream::Battle);
+        draw(&busy, RngStream::Ai);
+        assert_eq!(
+            draw(&quiet, RngStream::Starmap),
+            draw(&busy, RngStream::Starmap)
+        );
+    }
+
+    #[test]
+    fn restore_resumes_every_stream() {
+        let rng = GameRng::new(11);
+        draw(&rng, RngStream::Orbit);
+        let restored = GameRng::restore(&rng.snapshot());
+        for stream in RngStream::ALL {
+            assert_eq!(draw(&rng, stream), draw(&restored, stream));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use super::model::*;
+use super::planet::PlanetBusiness;
+use super::player::*;
+use super::starmap::Starmap;
+use super::GameState;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionError {
+    UnknownPlanet(usize),
+    NotOwner(usize),
+    InsufficientResources(usize),
+    NoShipsToMove(usize),
+    SamePlanet(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    ShipAdded(usize),
+    ShipsMoved(usize, usize, usize),
+    Waited,
+}
+
+/// Checks an action of a contender against the current game state, as the single
+/// authority on what may be performed: planets are looked up by id, ships may only
+/// be added on owned planets with enough extracted resources, and fleets must leave
+/// for another planet with at least one ship.
+pub fn validate_action<T, U>(
+    game_state: &GameState<T, U>,
+    contender_id: usize,
+    player_action: PlayerAction,
+) -> Result<ActionOutcome, ActionError>
+where
+    T: Starmap,
+    U: Player,
+{
+    let rules = game_state.get_rules();
+    match player_action {
+        PlayerAction::AddShip(on) => {
+            let planet = get_planet_properties(game_state, on.id)?;
+            if planet.contender_id != contender_id {
+                return Err(ActionError::NotOwner(planet.id));
+            }
+            if planet.extracted < rules.add_ship_resource_cost {
+                return Err(ActionError::InsufficientResources(planet.id));
+            }
+            Ok(ActionOutcome::ShipAdded(planet.id))
+        }
+        PlayerAction::MoveShips(from, to, size) => {
+            let planet_from = get_planet_properties(game_state, from.id)?;
+            let planet_to = get_planet_properties(game_state, to.id)?;
+            if planet_from.id == planet_to.id {
+                return Err(ActionError::SamePlanet(planet_from.id));
+            }
+            let ships_count = game_state
+                .get_players()
+                .iter()
+                .find(|player| player.properties().id == contender_id)
+                .map(|player| player.get_ships_on_planet(planet_from).len())
+                .unwrap_or(0);
+            let count = PlanetBusiness::new(*rules).count_ships_to_move(ships_count, size);
+            if count == 0 {
+                return Err(ActionError::NoShipsToMove(planet_from.id));
+            }
+            Ok(ActionOutcome::ShipsMoved(
+                planet_from.id,
+                planet_to.id,
+                count,
+            ))
+        }
+        PlayerAction::Wait => Ok(ActionOutcome::Waited),
+    }
+}
+
+fn get_planet_properties<T, U>(
+    game_state: &GameState<T, U>,
+    planet_id: usize,
+) -> Result<CelestialProperties, ActionError>
+where
+    T: Starmap,
+    U: Player,
+{
+    let starmap = game_state.get_starmap();
+    if planet_id >= starmap.get_planets().len() {
+        return Err(ActionError::UnknownPlanet(planet_id));
+    }
+    Ok(starmap.get_planet_properties(planet_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::sim::player::SimPlayer;
+    use crate::local::sim::starmap::SimStarmap;
+    use crate::local::sim::SimGame;
+
+    const PLAYER: usize = 0;
+
+    /// Home planet of the player, of its opponent, and a neutral planet.
+    fn planets(
+        game: &SimGame,
+    ) -> (
+        CelestialProperties,
+        CelestialProperties,
+        CelestialProperties,
+    ) {
+        let starmap = game.get_game_state().get_starmap();
+        let planets: Vec<CelestialProperties> = (0..starmap.get_planets().len())
+            .map(|id| starmap.get_planet_properties(id))
+            .collect();
+        let owned_by = |contender_id| {
+            *planets
+                .iter()
+                .find(|planet| planet.contender_id == contender_id)
+                .unwrap()
+        };
+        (owned_by(PLAYER), owned_by(1), owned_by(usize::MAX))
+    }
+
+    fn validate(game: &SimGame, action: PlayerAction) -> Result<ActionOutcome, ActionError> {
+        let game_state: &GameState<SimStarmap, SimPlayer> = game.get_game_state();
+        validate_action(game_state, PLAYER, action)
+    }
+
+    #[test]
+    fn unknown_planets_are_rejected() {
+        let game = SimGame::new(1, 3, 1);
+        let (home, _, _) = planets(&game);
+        let unknown = CelestialProperties { id: 99, ..home };
+
+        assert_eq!(
+            validate(&game, PlayerAction::AddShip(unknown)),
+            Err(ActionError::UnknownPlanet(99))
+        );
+        assert_eq!(
+            validate(
+                &game,
+                PlayerAction::MoveShips(home, unknown, FleetSize::Count(1))
+            ),
+            Err(ActionError::UnknownPlanet(99))
+        );
+    }
+
+    #[test]
+    fn ships_are_added_on_owned_planets_only() {
+        let game = SimGame::new(1, 3, 1);
+        let (_, opponent, neutral) = planets(&game);
+
+        assert_eq!(
+            validate(&game, PlayerAction::AddShip(opponent)),
+            Err(ActionError::NotOwner(opponent.id))
+        );
+        assert_eq!(
+            validate(&game, PlayerAction::AddShip(neutral)),
+            Err(ActionError::NotOwner(neutral.id))
+        );
+    }
+
+    #[test]
+    fn ships_need_extracted_resources() {
+        let game = SimGame::new(1, 3, 1);
+        let (home, _, _) = planets(&game);
+        assert!(home.extracted < game.get_game_state().get_rules().add_ship_resource_cost);
+
+        assert_eq!(
+            validate(&game, PlayerAction::AddShip(home)),
+            Err(ActionError::InsufficientResources(home.id))
+        );
+    }
+
+    #[test]
+    fn fleets_need_ships_to_move() {
+        let game = SimGame::new(1, 3, 1);
+        let (home, opponent, neutral) = planets(&game);
+
+        assert_eq!(
+            validate(
+                &game,
+                PlayerAction::MoveShips(neutral, home, FleetSize::Count(1))
+            ),
+            Err(ActionError::NoShipsToMove(neutral.id))
+        );
+        assert_eq!(
+            validate(
+                &game,
+                PlayerAction::MoveShips(home, opponent, FleetSize::Percent(50))
+            ),
+            Err(ActionError::NoShipsToMove(home.id))
+        );
+        assert_eq!(
+            validate(
+                &game,
+                PlayerAction::MoveShips(home, opponent, FleetSize::Count(1))
+            ),
+            Ok(ActionOutcome::ShipsMoved(home.id, opponent.id, 1))
+        );
+    }
+
+    #[test]
+    fn fleets_must_leave_their_planet() {
+        let game = SimGame::new(1, 3, 1);
+        let (home, _, _) = planets(&game);
+
+        assert_eq!(
+            validate(
+                &game,
+                PlayerAction::MoveShips(home, home, FleetSize::Count(1))
+            ),
+            Err(ActionError::SamePlanet(home.id))
+        );
+        assert_eq!(
+            validate(&game, PlayerAction::Wait),
+            Ok(ActionOutcome::Waited)
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+use super::battle::BattleModel;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleSetError {
+    Io(String),
+    Format(String),
+    Invalid(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    pub add_player_resources_init: f32,
+    pub add_player_resources_inc: f32,
+    pub planet_resources_init: f32,
+    pub planet_resources_inc: f32,
+    pub add_ship_resource_cost: f32,
+    pub move_ship_fleet_percent: usize,
+    pub move_ship_speed_mult: f32,
+    pub battle_fighting_factor: f32,
+    pub battle_model: BattleModel,
+    pub planet_distance_min: f32,
+    pub planet_distance_max: f32,
+    pub ai_sight_range: Option<f32>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            add_player_resources_init: 200.0,
+            add_player_resources_inc: 0.0018,
+            planet_resources_init: 600.0,
+            planet_resources_inc: 0.0012,
+            add_ship_resource_cost: 10.0,
+            move_ship_fleet_percent: 50,
+            move_ship_speed_mult: 10.0,
+            battle_fighting_factor: 0.005,
+            battle_model: BattleModel::Stochastic,
+            planet_distance_min: 100.0,
+            planet_distance_max: 2000.0,
+            ai_sight_range: None,
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn from_json(json: &str) -> Result<Self, RuleSetError> {
+        let rules: RuleSet =
+            serde_json::from_str(json).map_err(|e| RuleSetError::Format(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn load(path: &str) -> Result<Self, RuleSetError> {
+        let json = fs::read_to_string(path).map_err(|e| RuleSetError::Io(e.to_string()))?;
+        RuleSet::from_json(&json)
+    }
+
+    /// Distance travelled by a fleet per second of game time.
+    pub fn fleet_speed(&self) -> f32 {
+        10.0 * self.move_ship_speed_mult
+    }
+
+    pub fn validate(&self) -> Result<(), RuleSetError> {
+        let positives = [
+            ("add_player_resources_init", self.add_player_resources_init),
+            ("add_player_resources_inc", self.add_player_resources_inc),
+            ("planet_resources_init", self.planet_resources_init),
+            ("planet_resources_inc", self.planet_resources_inc),
+            ("move_ship_speed_mult", self.move_ship_speed_mult),
+            ("battle_fighting_factor", self.battle_fighting_factor),
+            ("planet_distance_max", self.planet_distance_max),
+        ];
+        if let Some((name, value)) = positives
+            .iter()
+            .find(|(_, value)| !value.is_finite() || *value <= 0.0)
+        {
+            return Err(RuleSetError::Invalid(format!(
+                "{name} must be a positive number, got {value}"
+            )));
+        }
+        if !self.add_ship_resource_cost.is_finite() || self.add_ship_resource_cost < 1.0 {
+            return Err(RuleSetError::Invalid(format!(
+                "add_ship_resource_cost must be at least 1, got {}",
+                self.add_ship_resource_cost
+            )));
+        }
+        if self.move_ship_fleet_percent == 0 || self.move_ship_fleet_percent > 100 {
+            return Err(RuleSetError::Invalid(format!(
+                "move_ship_fleet_percent must be between 1 and 100, got {}",
+                self.move_ship_fleet_percent
+            )));
+        }
+        if !self.planet_distance_min.is_finite()
+            || self.planet_distance_min < 0.0
+            || self.planet_distance_min >= self.planet_distance_max
+        {
+            return Err(RuleSetError::Invalid(format!(
+                "planet_distance_min must be between 0 and planet_distance_max, got {}",
+                self.planet_distance_min
+            )));
+        }
+        match self.ai_sight_range {
+            Some(range) if !range.is_finite() || range <= 0.0 => Err(RuleSetError::Invalid(
+                format!("ai_sight_range must be a positive number, got {range}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type SetField = fn(&mut RuleSet, f32);
+
+    fn invalid_field(rules: RuleSet) -> String {
+        match rules.validate() {
+            Err(RuleSetError::Invalid(message)) => message.split(' ').next().unwrap().to_owned(),
+            result => panic!("expected an invalid rule set, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn default_rules_are_valid() {
+        assert_eq!(RuleSet::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn non_positive_values_are_invalid() {
+        let cases: [(&str, SetField); 7] = [
+            ("add_player_resources_init", |r, v| {
+                r.add_player_resources_init = v
+            }),
+            ("add_player_resources_inc", |r, v| {
+                r.add_player_resources_inc = v
+            }),
+            ("planet_resources_init", |r, v| r.planet_resources_init = v),
+            ("planet_resources_inc", |r, v| r.planet_resources_inc = v),
+            ("move_ship_speed_mult", |r, v| r.move_ship_speed_mult = v),
+            ("battle_fighting_factor", |r, v| {
+                r.battle_fighting_factor = v
+            }),
+            ("planet_distance_max", |r, v| r.planet_distance_max = v),
+        ];
+        for (name, set) in cases.iter() {
+            for value in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+                let mut rules = RuleSet::default();
+                set(&mut rules, value);
+                assert_eq!(invalid_field(rules), *name, "{name} = {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn ship_cost_below_one_is_invalid() {
+        let rules = RuleSet {
+            add_ship_resource_cost: 0.5,
+            ..RuleSet::default()
+        };
+        assert_eq!(invalid_field(rules), "add_ship_resource_cost");
+    }
+
+    #[test]
+    fn fleet_percent_out_of_range_is_invalid() {
+        for percent in [0, 101] {
+            let rules = RuleSet {
+                move_ship_fleet_percent: percent,
+                ..RuleSet::default()
+            };
+            assert_eq!(invalid_field(rules), "move_ship_fleet_percent");
+        }
+        let rules = RuleSet {
+            move_ship_fleet_percent: 100,
+            ..RuleSet::default()
+        };
+        assert_eq!(rules.validate(), Ok(()));
+    }
+
+    #[test]
+    fn distance_min_out_of_range_is_invalid() {
+        let defaults = RuleSet::default();
+        for distance in [-1.0, defaults.planet_distance_max, f32::NAN] {
+            let rules = RuleSet {
+                planet_distance_min: distance,
+                ..defaults
+            };
+            assert_eq!(invalid_field(rules), "planet_distance_min");
+        }
+    }
+
+    #[test]
+    fn sight_range_must_be_positive() {
+        for range in [0.0, -1.0, f32::NAN] {
+            let rules = RuleSet {
+                ai_sight_range: Some(range),
+                ..RuleSet::default()
+            };
+            assert_eq!(invalid_field(rules), "ai_sight_range");
+        }
+        let rules = RuleSet {
+            ai_sight_range: Some(300.0),
+            ..RuleSet::default()
+        };
+        assert_eq!(rules.validate(), Ok(()));
+    }
+
+    #[test]
+    fn json_rules_are_checked() {
+        let rules = RuleSet::from_json("{\"add_ship_resource_cost\": 20.0}").unwrap();
+        assert_eq!(rules.add_ship_resource_cost, 20.0);
+        assert_eq!(
+            rules.planet_resources_init,
+            RuleSet::default().planet_resources_init
+        );
+
+        assert!(matches!(
+            RuleSet::from_json("{\"add_ship_cost\": 20.0}"),
+            Err(RuleSetError::Format(_))
+        ));
+        assert!(matches!(
+            RuleSet::from_json("{\"move_ship_fleet_percent\": 0}"),
+            Err(RuleSetError::Invalid(_))
+        ));
+        assert!(matches!(
+            RuleSet::load("/nonexistent/rules.json"),
+            Err(RuleSetError::Io(_))
+        ));
+    }
+}
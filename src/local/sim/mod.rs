@@ -0,0 +1,352 @@
+pub mod planet;
+pub mod player;
+pub mod ship;
+pub mod starmap;
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use self::planet::{RefSimPlanet, SimPlanet};
+use self::player::SimPlayer;
+use self::ship::SimShip;
+use self::starmap::SimStarmap;
+use crate::local::event::GameEvent;
+use crate::local::model::*;
+use crate::local::player::*;
+use crate::local::replay::*;
+use crate::local::rng::RngStream;
+use crate::local::rules::*;
+use crate::local::ruleset::RuleSet;
+use crate::local::snapshot::GameSnapshot;
+use crate::local::starmap::layout::StarmapLayout;
+use crate::local::starmap::*;
+use crate::local::GameState;
+
+/// Headless counterpart of the godot2d renderer: it drives a `GameState` tick by tick,
+/// running AI, ship travel, battles and extraction without any engine.
+pub struct SimGame {
+    setup: GameSetup,
+    game_state: GameState<SimStarmap, SimPlayer>,
+}
+
+impl SimGame {
+    pub const WIDTH: f32 = 1024.0;
+    pub const HEIGHT: f32 = 600.0;
+    pub const DIFFICULTY: usize = 2;
+
+    pub fn demo(players_count: usize, planets_count: usize, seed: u64) -> Self {
+        SimGame::from_setup(GameSetup {
+            seed,
+            players_count,
+            planets_count,
+            difficulty: Self::DIFFICULTY,
+            ai_difficulties: vec![],
+            ai_personalities: vec![],
+            demo: true,
+            rules: RuleSet::default(),
+            layout: StarmapLayout::default(),
+        })
+    }
+
+    pub fn new(ais_count: usize, planets_count: usize, seed: u64) -> Self {
+        SimGame::from_setup(GameSetup {
+            seed,
+            players_count: ais_count + 1,
+            planets_count,
+            difficulty: Self::DIFFICULTY,
+            ai_difficulties: vec![],
+            ai_personalities: vec![],
+            demo: false,
+            rules: RuleSet::default(),
+            layout: StarmapLayout::default(),
+        })
+    }
+
+    pub fn from_setup(setup: GameSetup) -> Self {
+        let mut sim_game = SimGame {
+            setup,
+            game_state: GameState::new(),
+        };
+        sim_game.start();
+        sim_game
+    }
+
+    pub fn replay(replay: Replay) -> Self {
+        let mut sim_game = SimGame::from_setup(replay.setup.clone());
+        sim_game.game_state.set_replayer(Replayer::new(replay));
+        sim_game
+    }
+
+    pub fn restore(snapshot: &GameSnapshot) -> Self {
+        let mut sim_game = SimGame {
+            setup: snapshot.setup.clone(),
+            game_state: GameState::new(),
+        };
+
+        let planets: Vec<RefSimPlanet> = snapshot
+            .planets
+            .iter()
+            .map(|planet| Rc::new(SimPlanet::restore(planet, snapshot.setup.rules)))
+            .collect();
+        let players = snapshot
+            .players
+            .iter()
+            .map(|player| {
+                let player_planets = player
+                    .planets
+                    .iter()
+                    .map(|planet_id| planets[*planet_id].clone())
+                    .collect();
+                let ships = player
+                    .ships
+                    .iter()
+                    .map(|ship| Rc::new(SimShip::restore(*ship)))
+                    .collect();
+                Rc::new(SimPlayer::restore(player.properties, player_planets, ships))
+            })
+            .collect();
+
+        let mut starmap = SimStarmap::default();
+        starmap.set_planets(planets);
+        sim_game.game_state.restore(snapshot, players);
+        sim_game.game_state.set_starmap(starmap);
+        sim_game
+    }
+
+    fn start(&mut self) {
+        self.game_state.reset();
+        self.game_state.set_seed(self.setup.seed);
+        self.game_state.set_rules(self.setup.rules);
+        self.game_state.set_difficulty(self.setup.get_difficulty());
+        self.game_state
+            .set_ai_difficulties(self.setup.get_ai_difficulties());
+        self.game_state
+            .set_ai_personalities(self.setup.ai_personalities.clone());
+        self.game_state.start_recording(self.setup.clone());
+
+        let rules = self.setup.rules;
+        let layout = self.setup.layout;
+        let planets_count = self.setup.planets_count;
+        let rng = self.game_state.get_rng();
+        let mut starmap = SimStarmap::new(planets_count)
+            .with_generator(|id| {
+                let planet = rng.with(RngStream::Starmap, |rng| {
+                    SimPlanet::new(
+                        id,
+                        planets_count,
+                        (Self::WIDTH, Self::HEIGHT),
+                        layout,
+                        rules,
+                        rng,
+                    )
+                });
+                Rc::new(planet)
+            })
+            .with_validator(|planet1, planet2| {
+                let distance = SimStarmap::get_distance_between(planet1, planet2);
+                distance > rules.planet_distance_min && distance < rules.planet_distance_max
+            })
+            .with_cleaner(|_| ())
+            .build();
+
+        let demo = self.setup.demo;
+        starmap
+            .get_planets_by_max_distance(self.setup.players_count)
+            .iter()
+            .enumerate()
+            .for_each(|(index, planet)| {
+                planet.set_resources(
+                    rules.add_player_resources_init,
+                    rules.add_player_resources_inc,
+                );
+                SimPlanet::add_player(planet, &mut self.game_state, index > 0 || demo);
+            });
+
+        self.game_state.set_starmap(starmap);
+    }
+
+    pub fn with_ai_workers(mut self, workers_count: usize, budget: Duration) -> Self {
+        self.game_state.start_ai_workers(workers_count, budget);
+        self
+    }
+
+    pub fn get_game_state(&self) -> &GameState<SimStarmap, SimPlayer> {
+        &self.game_state
+    }
+
+    pub fn get_game_state_mut(&mut self) -> &mut GameState<SimStarmap, SimPlayer> {
+        &mut self.game_state
+    }
+
+    pub fn get_setup(&self) -> GameSetup {
+        self.setup.clone()
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        self.game_state.snapshot(self.setup.clone())
+    }
+
+    pub fn get_replay(&self) -> Option<Replay> {
+        self.game_state.get_replay()
+    }
+
+    pub fn tick(&mut self, delta: f64) {
+        for _ in 0..self.game_state.add_time_delta(delta) {
+            self.step();
+        }
+    }
+
+    fn step(&mut self) {
+        self.game_state.next_tick();
+
+        let moves: Vec<(usize, PlayerAction)> = if self.game_state.is_replaying() {
+            self.game_state.update_replay()
+        } else {
+            self.game_state
+                .update_ai()
+                .iter()
+                .map(|(ai_player, ai_move)| (ai_player.id, *ai_move))
+                .collect()
+        };
+        for (player_id, player_move) in moves.iter() {
+            let player = self
+                .game_state
+                .get_players()
+                .iter()
+                .find(|p| p.properties().id == *player_id)
+                .unwrap();
+            // Rejected actions are reported by a GameEvent::ActionRejected.
+            let _ = SimGame::perform_action(&self.game_state, player, *player_move);
+        }
+
+        let planets = self.game_state.get_starmap().get_planets();
+
+        let players = self.game_state.get_players();
+        self.game_state.update_fleets().iter().for_each(|fleet| {
+            let player = players
+                .iter()
+                .find(|p| p.properties().id == fleet.contender_id)
+                .unwrap();
+            let destination = &planets[fleet.destination_id];
+            fleet
+                .ships
+                .iter()
+                .for_each(|ship| player.land(*ship, destination));
+        });
+
+        planets
+            .iter()
+            .for_each(|planet| planet.battle(&self.game_state));
+
+        if self.game_state.is_extraction_tick() {
+            planets.iter().for_each(|planet| planet.extract(players));
+        }
+
+        self.game_state.update_standings();
+    }
+
+    pub fn run(&mut self, delta: f64, time_limit: f64) -> Option<Rc<SimPlayer>> {
+        while self.game_state.get_time() < time_limit && !self.game_state.get_clock().is_paused() {
+            self.tick(delta);
+            let (winner, _) = self.game_state.check_game_over();
+            if winner.is_some() {
+                return winner;
+            }
+        }
+        None
+    }
+
+    pub fn perform_action(
+        game_state: &GameState<SimStarmap, SimPlayer>,
+        player: &SimPlayer,
+        player_action: PlayerAction,
+    ) -> Result<ActionOutcome, ActionError> {
+        let outcome = match validate_action(game_state, player.properties().id, player_action) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                game_state.emit_event(GameEvent::ActionRejected(player.properties().id, err));
+                return Err(err);
+            }
+        };
+        game_state.record_action(player.properties().id, player_action);
+        let rules = game_state.get_rules();
+        let planets = game_state.get_starmap().get_planets();
+        match player_action {
+            PlayerAction::AddShip(on) => {
+                planets[on.id].add_ship(rules.add_ship_resource_cost, player, game_state);
+            }
+            PlayerAction::MoveShips(from, to, size) => {
+                planets[from.id].move_ships(size, player, &planets[to.id], game_state);
+            }
+            _ => (),
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::clock::SimClock;
+
+    const TIME_LIMIT: f64 = 600.0;
+
+    #[test]
+    fn seeded_game_plays_to_game_over() {
+        let mut game = SimGame::demo(2, 3, 1);
+        let winner = game.run(SimClock::TICK_DURATION, TIME_LIMIT);
+
+        let (game_over_winner, losers) = game.get_game_state().check_game_over();
+        let winner = winner.expect("the game should end before the time limit");
+        assert_eq!(
+            game_over_winner.map(|player| player.properties().id),
+            Some(winner.properties().id)
+        );
+        assert!(winner.is_playing());
+        assert_eq!(losers.len(), 1);
+        assert!(losers.iter().all(|loser| !loser.is_playing()));
+        assert!(game.get_game_state().get_time() < TIME_LIMIT);
+    }
+
+    #[test]
+    fn same_seed_gives_same_game() {
+        let mut first = SimGame::demo(2, 3, 7);
+        let mut second = SimGame::demo(2, 3, 7);
+        let first_winner = first.run(SimClock::TICK_DURATION, TIME_LIMIT);
+        let second_winner = second.run(SimClock::TICK_DURATION, TIME_LIMIT);
+
+        assert_eq!(
+            first_winner.map(|player| player.properties().id),
+            second_winner.map(|player| player.properties().id)
+        );
+        assert_eq!(
+            first.snapshot().to_json().unwrap(),
+            second.snapshot().to_json().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejected_actions_are_emitted() {
+        let game = SimGame::new(1, 3, 1);
+        let game_state = game.get_game_state();
+        let events = game_state.subscribe_events();
+        let opponent = game_state.get_players()[1].clone();
+        let starmap = game_state.get_starmap();
+        let home = (0..starmap.get_planets().len())
+            .map(|id| starmap.get_planet_properties(id))
+            .find(|planet| planet.contender_id == 0)
+            .unwrap();
+
+        let result = SimGame::perform_action(game_state, &opponent, PlayerAction::AddShip(home));
+        assert_eq!(result, Err(ActionError::NotOwner(home.id)));
+        let rejected: Vec<(usize, ActionError)> = game_state
+            .poll_events(events)
+            .into_iter()
+            .filter_map(|(_, event)| match event {
+                GameEvent::ActionRejected(player_id, err) => Some((player_id, err)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rejected, vec![(1, ActionError::NotOwner(home.id))]);
+    }
+}
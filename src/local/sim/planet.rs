@@ -0,0 +1,230 @@
+use rand::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::player::SimPlayer;
+use super::ship::{RefSimShip, SimShip};
+use super::starmap::SimStarmap;
+use crate::local::battle::BattleState;
+use crate::local::event::GameEvent;
+use crate::local::model::*;
+use crate::local::planet::PlanetBusiness;
+use crate::local::player::*;
+use crate::local::rng::RngStream;
+use crate::local::ruleset::RuleSet;
+use crate::local::snapshot::PlanetSnapshot;
+use crate::local::starmap::layout::StarmapLayout;
+use crate::local::starmap::*;
+use crate::local::GameState;
+
+pub type RefSimPlanet = Rc<SimPlanet>;
+
+pub struct SimPlanet {
+    business: PlanetBusiness,
+    position: (f32, f32),
+    properties: RefCell<CelestialProperties>,
+}
+
+impl Celestial for SimPlanet {
+    fn properties(&self) -> CelestialProperties {
+        *self.properties.borrow()
+    }
+}
+
+impl SimPlanet {
+    pub fn new<R: Rng>(
+        id: usize,
+        count: usize,
+        (width, height): (f32, f32),
+        layout: StarmapLayout,
+        rules: RuleSet,
+        rng: &mut R,
+    ) -> Self {
+        let resources_initial =
+            rng.gen_range((rules.planet_resources_init * 0.1)..rules.planet_resources_init);
+        let radius = 20.0 * rng.gen_range(0.5..2.5);
+        let diameter = 2.0 * radius;
+        let (x_ratio, y_ratio) =
+            layout.place(id, count, rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+        let x = (x_ratio * width).clamp(diameter, width - diameter);
+        let y = (y_ratio * height).clamp(diameter, height - diameter);
+
+        let properties = CelestialProperties {
+            id,
+            contender_id: usize::MAX,
+            radius,
+            resources: resources_initial,
+            resources_increase: resources_initial
+                * rng.gen_range((rules.planet_resources_inc * 0.1)..rules.planet_resources_inc),
+            extracted: 0.0,
+        };
+        SimPlanet {
+            business: PlanetBusiness::new(rules),
+            position: (x, y),
+            properties: RefCell::new(properties),
+        }
+    }
+
+    pub fn restore(snapshot: &PlanetSnapshot, rules: RuleSet) -> Self {
+        let business = PlanetBusiness::new(rules);
+        business.set_battle_state(snapshot.battle.clone());
+        SimPlanet {
+            business,
+            position: snapshot.position,
+            properties: RefCell::new(snapshot.properties),
+        }
+    }
+
+    pub fn get_position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    pub fn get_battle_state(&self) -> BattleState {
+        self.business.get_battle_state()
+    }
+
+    pub fn set_resources(&self, initial: f32, inc: f32) {
+        let mut props = self.properties.borrow_mut();
+        self.business.resources_init(&mut props, initial, inc);
+    }
+
+    pub fn add_player(
+        planet: &RefSimPlanet,
+        game_state: &mut GameState<SimStarmap, SimPlayer>,
+        is_bot: bool,
+    ) {
+        let mut props = planet.properties.borrow_mut();
+        props.contender_id = game_state.get_players().len();
+        let player_props = ContenderProperties {
+            id: props.contender_id,
+            color: get_color(props.contender_id),
+            bot: is_bot,
+        };
+        let ship = Rc::new(SimShip::new(player_props, game_state.next_vessel_id()));
+        drop(props);
+        ship.orbit(planet);
+
+        let player = SimPlayer::new(player_props.id, planet.clone(), ship, is_bot);
+        game_state.add_player(player.into());
+    }
+
+    pub fn add_ship(
+        &self,
+        resources_cost: f32,
+        player: &SimPlayer,
+        game_state: &GameState<SimStarmap, SimPlayer>,
+    ) {
+        let mut props = self.properties.borrow_mut();
+
+        if self
+            .business
+            .can_add_ship(&mut props, player.properties(), resources_cost)
+        {
+            drop(props);
+            let ship = Rc::new(SimShip::new(
+                player.properties(),
+                game_state.next_vessel_id(),
+            ));
+            ship.orbit(self);
+            game_state.emit_event(GameEvent::ShipBuilt(ship.properties()));
+            player.add_ship(ship);
+        }
+    }
+
+    pub fn move_ships(
+        &self,
+        size: FleetSize,
+        player: &SimPlayer,
+        destination: &RefSimPlanet,
+        game_state: &GameState<SimStarmap, SimPlayer>,
+    ) {
+        let props = self.properties();
+        let mut selected_ships: Vec<RefSimShip> = player
+            .ships
+            .borrow()
+            .iter()
+            .filter(|ship| ship.properties().celestial_id == props.id)
+            .cloned()
+            .collect();
+        let count: usize = self
+            .business
+            .count_ships_to_move(selected_ships.len(), size);
+        let ships = selected_ships
+            .drain(0..count)
+            .map(|ship| {
+                ship.leave_orbit();
+                ship.properties()
+            })
+            .collect();
+        game_state.launch_fleet(
+            player.properties().id,
+            props.id,
+            destination.properties().id,
+            ships,
+        );
+    }
+
+    pub fn extract(&self, players: &[Rc<SimPlayer>]) {
+        let mut props = self.properties.borrow_mut();
+        let ships_count = players
+            .iter()
+            .find(|player| player.properties().id == props.contender_id)
+            .map(|player| player.get_ships_on_planet(*props).len())
+            .unwrap_or(0);
+        self.business
+            .resources_update(&mut props, ships_count as i32);
+    }
+
+    pub fn battle(&self, game_state: &GameState<SimStarmap, SimPlayer>) {
+        let players = game_state.get_players();
+        let props = self.properties();
+
+        let ships_by_player_on_planet = game_state.get_ships_by_player_on_planet(props);
+        let result = game_state.get_rng().with(RngStream::Battle, |rng| {
+            self.business.battle(ships_by_player_on_planet, rng)
+        });
+
+        for casualty in result.get_casualties() {
+            if let Some(casualty_player) = players
+                .iter()
+                .find(|player| player.properties().id == casualty.contender_id)
+            {
+                casualty_player.remove_ship(casualty);
+                game_state.emit_event(GameEvent::ShipDestroyed(casualty));
+            }
+        }
+
+        if let Some(winner) = result.winner {
+            if winner.id == props.contender_id {
+                return;
+            }
+            if let Some(loser) = players
+                .iter()
+                .find(|player| player.properties().id == props.contender_id)
+            {
+                loser
+                    .planets
+                    .borrow_mut()
+                    .retain(|planet| planet.properties().id != props.id);
+            }
+            let winner = players
+                .iter()
+                .find(|player| player.properties().id == winner.id)
+                .unwrap();
+            self.properties.borrow_mut().contender_id = winner.properties().id;
+            let planet = game_state
+                .get_starmap()
+                .get_planets()
+                .into_iter()
+                .find(|planet| planet.properties().id == props.id)
+                .unwrap();
+            winner.planets.borrow_mut().push(planet);
+            game_state.emit_event(GameEvent::PlanetCaptured(
+                props.id,
+                props.contender_id,
+                winner.properties().id,
+            ));
+        }
+    }
+}
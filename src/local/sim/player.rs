@@ -0,0 +1,113 @@
+use std::cell::*;
+
+use super::planet::{RefSimPlanet, SimPlanet};
+use super::ship::RefSimShip;
+use crate::local::model::*;
+use crate::local::player::*;
+use crate::local::snapshot::PlayerSnapshot;
+
+pub struct SimPlayer {
+    pub planets: RefCell<Vec<RefSimPlanet>>,
+    pub ships: RefCell<Vec<RefSimShip>>,
+
+    properties: RefCell<ContenderProperties>,
+}
+
+impl Contender for SimPlayer {
+    fn properties(&self) -> ContenderProperties {
+        *self.properties.borrow()
+    }
+}
+
+impl Player for SimPlayer {
+    type CelestialType = RefSimPlanet;
+    type VesselType = RefSimShip;
+
+    fn new(id: usize, planet: RefSimPlanet, ship: RefSimShip, bot: bool) -> Self {
+        let properties = ContenderProperties {
+            id,
+            color: get_color(id),
+            bot,
+        };
+        SimPlayer {
+            properties: RefCell::new(properties),
+            planets: RefCell::new(vec![planet]),
+            ships: RefCell::new(vec![ship]),
+        }
+    }
+
+    fn destroy(&self) {
+        self.ships.borrow_mut().clear();
+        self.planets.borrow_mut().clear();
+    }
+
+    fn add_ship(&self, ship: RefSimShip) {
+        self.ships.borrow_mut().push(ship);
+    }
+
+    fn is_playing(&self) -> bool {
+        !self.ships.borrow().is_empty()
+    }
+
+    fn get_ships_on_planet(&self, planet_props: CelestialProperties) -> Vec<VesselProperties> {
+        self.ships
+            .borrow()
+            .iter()
+            .map(|ship| ship.properties())
+            .filter(|ship_props| ship_props.celestial_id == planet_props.id)
+            .collect()
+    }
+
+    fn snapshot(&self) -> PlayerSnapshot {
+        PlayerSnapshot {
+            properties: self.properties(),
+            planets: self
+                .planets
+                .borrow()
+                .iter()
+                .map(|planet| planet.properties().id)
+                .collect(),
+            ships: self
+                .ships
+                .borrow()
+                .iter()
+                .map(|ship| ship.properties())
+                .collect(),
+        }
+    }
+}
+
+impl SimPlayer {
+    pub fn restore(
+        properties: ContenderProperties,
+        planets: Vec<RefSimPlanet>,
+        ships: Vec<RefSimShip>,
+    ) -> Self {
+        SimPlayer {
+            properties: RefCell::new(properties),
+            planets: RefCell::new(planets),
+            ships: RefCell::new(ships),
+        }
+    }
+
+    pub fn remove_ship(&self, ship_props: VesselProperties) {
+        let mut ships = self.ships.borrow_mut();
+        if let Some(index) = ships.iter().position(|ship| {
+            ship.properties().id == ship_props.id
+                && ship.properties().celestial_id == ship_props.celestial_id
+        }) {
+            ships.remove(index);
+        }
+    }
+
+    pub fn land(&self, ship_props: VesselProperties, planet: &SimPlanet) {
+        if let Some(ship) = self
+            .ships
+            .borrow()
+            .iter()
+            .find(|ship| ship.properties().id == ship_props.id && ship.is_travelling())
+        {
+            ship.orbit(planet);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::planet::SimPlanet;
+use crate::local::model::*;
+
+pub type RefSimShip = Rc<SimShip>;
+
+pub struct SimShip {
+    properties: RefCell<VesselProperties>,
+}
+
+impl Vessel for SimShip {
+    fn properties(&self) -> VesselProperties {
+        *self.properties.borrow()
+    }
+}
+
+impl SimShip {
+    pub fn new(player_props: ContenderProperties, id: usize) -> Self {
+        SimShip {
+            properties: RefCell::new(VesselProperties {
+                id,
+                contender_id: player_props.id,
+                celestial_id: 0,
+            }),
+        }
+    }
+
+    pub fn restore(properties: VesselProperties) -> Self {
+        SimShip {
+            properties: RefCell::new(properties),
+        }
+    }
+
+    pub fn orbit(&self, planet: &SimPlanet) {
+        let mut props = self.properties.borrow_mut();
+        props.celestial_id = planet.properties().id;
+    }
+
+    pub fn leave_orbit(&self) {
+        let mut props = self.properties.borrow_mut();
+        props.celestial_id = usize::MAX;
+    }
+
+    pub fn is_travelling(&self) -> bool {
+        self.properties().celestial_id == usize::MAX
+    }
+}
@@ -0,0 +1,55 @@
+use super::planet::RefSimPlanet;
+use crate::local::model::*;
+use crate::local::snapshot::PlanetSnapshot;
+use crate::local::starmap::builder::StarmapBuilder;
+use crate::local::starmap::*;
+
+#[derive(Default)]
+pub struct SimStarmap {
+    planets: Vec<RefSimPlanet>,
+}
+
+impl Starmap for SimStarmap {
+    type CelestialType = RefSimPlanet;
+
+    fn get_planets(&self) -> Vec<RefSimPlanet> {
+        self.planets.clone()
+    }
+
+    fn get_planet_properties(&self, planet_id: usize) -> CelestialProperties {
+        self.planets.get(planet_id).unwrap().properties()
+    }
+
+    fn set_planets(&mut self, planets: Vec<RefSimPlanet>) {
+        self.planets = planets;
+    }
+
+    fn new<F, G, H>(count: usize) -> StarmapBuilder<RefSimPlanet, SimStarmap, F, G, H>
+    where
+        F: FnMut(usize) -> RefSimPlanet,
+        G: Fn(&RefSimPlanet, &RefSimPlanet) -> bool,
+        H: Fn(&RefSimPlanet),
+        Self: Sized,
+    {
+        StarmapBuilder::new(count, SimStarmap::default())
+    }
+
+    fn destroy(&self) {}
+
+    fn snapshot(&self) -> Vec<PlanetSnapshot> {
+        self.planets
+            .iter()
+            .map(|planet| PlanetSnapshot {
+                properties: planet.properties(),
+                position: planet.get_position(),
+                battle: planet.get_battle_state(),
+            })
+            .collect()
+    }
+
+    fn get_distance_between(planet1: &RefSimPlanet, planet2: &RefSimPlanet) -> f32 {
+        let (x1, y1) = planet1.get_position();
+        let (x2, y2) = planet2.get_position();
+        ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt()
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+use super::ai::AiSnapshot;
+use super::battle::BattleState;
+use super::clock::SimClock;
+use super::fleet::Fleet;
+use super::model::*;
+use super::replay::GameSetup;
+use super::rng::GameRngSnapshot;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotError {
+    Io(String),
+    Format(String),
+    Version(u32),
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanetSnapshot {
+    pub properties: CelestialProperties,
+    pub position: (f32, f32),
+    pub battle: BattleState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    pub properties: ContenderProperties,
+    pub planets: Vec<usize>,
+    pub ships: Vec<VesselProperties>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub version: u32,
+    pub setup: GameSetup,
+    pub clock: SimClock,
+    pub rng: GameRngSnapshot,
+    pub planets: Vec<PlanetSnapshot>,
+    pub players: Vec<PlayerSnapshot>,
+    pub ais: Vec<AiSnapshot>,
+    pub fleets: Vec<Fleet>,
+}
+
+impl GameSnapshot {
+    pub fn to_json(&self) -> Result<String, SnapshotError> {
+        serde_json::to_string(self).map_err(|e| SnapshotError::Format(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let snapshot: GameSnapshot =
+            serde_json::from_str(json).map_err(|e| SnapshotError::Format(e.to_string()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(snapshot.version));
+        }
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    /// Checks that the planets, players, ships and fleets refer to each other, since
+    /// restoring a game looks them up without checks.
+    pub fn validate(&self) -> Result<(), SnapshotError> {
+        let planets_count = self.planets.len();
+        if let Some((index, planet)) = self
+            .planets
+            .iter()
+            .enumerate()
+            .find(|(index, planet)| planet.properties.id != *index)
+        {
+            return Err(SnapshotError::Invalid(format!(
+                "planet {index} has id {}",
+                planet.properties.id
+            )));
+        }
+        for fleet in self.fleets.iter() {
+            if fleet.origin_id >= planets_count || fleet.destination_id >= planets_count {
+                return Err(SnapshotError::Invalid(format!(
+                    "fleet {} travels between unknown planets {} and {}",
+                    fleet.id, fleet.origin_id, fleet.destination_id
+                )));
+            }
+        }
+        for player in self.players.iter() {
+            let player_id = player.properties.id;
+            if let Some(planet_id) = player.planets.iter().find(|id| **id >= planets_count) {
+                return Err(SnapshotError::Invalid(format!(
+                    "player {player_id} owns unknown planet {planet_id}"
+                )));
+            }
+            for ship in player.ships.iter() {
+                let is_travelling = ship.celestial_id == usize::MAX;
+                if !is_travelling && ship.celestial_id >= planets_count {
+                    return Err(SnapshotError::Invalid(format!(
+                        "ship {} orbits unknown planet {}",
+                        ship.id, ship.celestial_id
+                    )));
+                }
+                let is_in_fleet = self.fleets.iter().any(|fleet| {
+                    fleet.contender_id == player_id
+                        && fleet
+                            .ships
+                            .iter()
+                            .any(|fleet_ship| fleet_ship.id == ship.id)
+                });
+                if is_travelling && !is_in_fleet {
+                    return Err(SnapshotError::Invalid(format!(
+                        "ship {} travels without fleet",
+                        ship.id
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SnapshotError> {
+        fs::write(path, self.to_json()?).map_err(|e| SnapshotError::Io(e.to_string()))
+    }
+
+    pub fn load(path: &str) -> Result<Self, SnapshotError> {
+        let json = fs::read_to_string(path).map_err(|e| SnapshotError::Io(e.to_string()))?;
+        GameSnapshot::from_json(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::sim::SimGame;
+
+    #[test]
+    fn restored_game_continues_like_the_original() {
+        let mut game = SimGame::demo(2, 3, 5);
+        game.run(SimClock::TICK_DURATION, 5.0);
+
+        let path = std::env::temp_dir().join(format!(
+            "remote-colonies-snapshot-test-{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        game.snapshot().save(path).unwrap();
+        let loaded = GameSnapshot::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            loaded.to_json().unwrap(),
+            game.snapshot().to_json().unwrap()
+        );
+
+        let mut restored = SimGame::restore(&loaded);
+        let winner = game.run(SimClock::TICK_DURATION, 600.0);
+        let restored_winner = restored.run(SimClock::TICK_DURATION, 600.0);
+        assert_eq!(
+            winner.map(|player| player.properties().id),
+            restored_winner.map(|player| player.properties().id)
+        );
+        assert_eq!(
+            game.snapshot().to_json().unwrap(),
+            restored.snapshot().to_json().unwrap()
+        );
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut snapshot = SimGame::demo(2, 3, 5).snapshot();
+        for version in [0, SNAPSHOT_VERSION + 1] {
+            snapshot.version = version;
+            let json = snapshot.to_json().unwrap();
+            assert_eq!(
+                GameSnapshot::from_json(&json).unwrap_err(),
+                SnapshotError::Version(version)
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(matches!(
+            GameSnapshot::from_json("{\"version\":1}"),
+            Err(SnapshotError::Format(_))
+        ));
+        assert!(matches!(
+            GameSnapshot::load("/nonexistent/snapshot.json"),
+            Err(SnapshotError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn dangling_references_are_rejected() {
+        let mut game = SimGame::demo(2, 3, 5);
+        game.run(SimClock::TICK_DURATION, 5.0);
+        let snapshot = game.snapshot();
+        let planets_count = snapshot.planets.len();
+        assert_eq!(snapshot.validate(), Ok(()));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.planets[0].properties.id = 1;
+        assert!(matches!(corrupt.validate(), Err(SnapshotError::Invalid(_))));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.players[0].planets.push(planets_count);
+        assert!(matches!(corrupt.validate(), Err(SnapshotError::Invalid(_))));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.players[0].ships[0].celestial_id = planets_count;
+        assert!(matches!(corrupt.validate(), Err(SnapshotError::Invalid(_))));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.fleets.clear();
+        corrupt.players[0].ships[0].celestial_id = usize::MAX;
+        let json = corrupt.to_json().unwrap();
+        assert!(matches!(
+            GameSnapshot::from_json(&json),
+            Err(SnapshotError::Invalid(_))
+        ));
+
+        let mut corrupt = snapshot;
+        let fleet_ship = corrupt.players[0].ships[0];
+        corrupt.fleets.push(Fleet {
+            id: 0,
+            contender_id: corrupt.players[0].properties.id,
+            origin_id: 0,
+            destination_id: planets_count,
+            ships: vec![fleet_ship],
+            distance: 1.0,
+            departure_time: 0.0,
+            arrival_time: 1.0,
+        });
+        assert!(matches!(corrupt.validate(), Err(SnapshotError::Invalid(_))));
+    }
+}
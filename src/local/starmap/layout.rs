@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts::{PI, TAU};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StarmapLayout {
+    #[default]
+    Random,
+    Spiral,
+    Clusters,
+    Rings,
+    HexGrid,
+}
+
+impl StarmapLayout {
+    pub const ALL: [StarmapLayout; 5] = [
+        StarmapLayout::Random,
+        StarmapLayout::Spiral,
+        StarmapLayout::Clusters,
+        StarmapLayout::Rings,
+        StarmapLayout::HexGrid,
+    ];
+
+    /// Indexes are the positions in the HUD layout list, starting from Random.
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL.get(index).copied().unwrap_or_default()
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            StarmapLayout::Random => "Random",
+            StarmapLayout::Spiral => "Spiral",
+            StarmapLayout::Clusters => "Clusters",
+            StarmapLayout::Rings => "Rings",
+            StarmapLayout::HexGrid => "Hex grid",
+        }
+    }
+
+    /// Places the planet `id` among `count` as ratios of the starmap width and
+    /// height. The two random ratios are the position itself in a random layout,
+    /// and jitter the planet around its slot otherwise, so that the planets the
+    /// starmap builder rejects come back at a different place.
+    pub fn place(&self, id: usize, count: usize, x_ratio: f32, y_ratio: f32) -> (f32, f32) {
+        let count = count.max(1);
+        let (x_jitter, y_jitter) = (x_ratio - 0.5, y_ratio - 0.5);
+        match self {
+            StarmapLayout::Random => (x_ratio, y_ratio),
+            StarmapLayout::Spiral => {
+                let arm = (id % 2) as f32;
+                let progress = (id as f32 + 0.5) / count as f32;
+                let angle = arm * PI + progress * 3.0 * PI + x_jitter * 0.5;
+                let radius = 0.08 + 0.36 * progress + y_jitter * 0.06;
+                Self::polar(angle, radius)
+            }
+            StarmapLayout::Clusters => {
+                let clusters_count = (count / 3).clamp(2, 6);
+                let cluster = id % clusters_count;
+                let cluster_angle = TAU * cluster as f32 / clusters_count as f32;
+                let (center_x, center_y) = Self::polar(cluster_angle, 0.3);
+                let angle = TAU * x_ratio;
+                let radius = 0.16 * y_ratio.sqrt();
+                (
+                    center_x + radius * angle.cos(),
+                    center_y + radius * angle.sin(),
+                )
+            }
+            StarmapLayout::Rings => {
+                let inner_count = count / 3;
+                let (index, ring_count, radius) = if id < inner_count {
+                    (id, inner_count, 0.2)
+                } else {
+                    (id - inner_count, count - inner_count, 0.42)
+                };
+                let angle = TAU * (index as f32 + 0.5 + x_jitter * 0.3) / ring_count as f32;
+                Self::polar(angle, radius + y_jitter * 0.04)
+            }
+            StarmapLayout::HexGrid => {
+                let columns = (count as f32).sqrt().ceil() as usize;
+                let rows = (count as f32 / columns as f32).ceil() as usize;
+                let (column, row) = (id % columns, id / columns);
+                let shift = (row % 2) as f32 * 0.5;
+                (
+                    (column as f32 + 0.5 + shift + x_jitter * 0.2) / (columns as f32 + 0.5),
+                    (row as f32 + 0.5 + y_jitter * 0.2) / rows as f32,
+                )
+            }
+        }
+    }
+
+    fn polar(angle: f32, radius: f32) -> (f32, f32) {
+        (0.5 + radius * angle.cos(), 0.5 + radius * angle.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::replay::GameSetup;
+    use crate::local::ruleset::RuleSet;
+    use crate::local::sim::starmap::SimStarmap;
+    use crate::local::sim::SimGame;
+    use crate::local::starmap::Starmap;
+
+    const MAX_PLANETS: usize = 20;
+
+    #[test]
+    fn layouts_are_listed_in_order() {
+        for (index, layout) in StarmapLayout::ALL.iter().enumerate() {
+            assert_eq!(StarmapLayout::from_index(index), *layout);
+        }
+        assert_eq!(
+            StarmapLayout::from_index(StarmapLayout::ALL.len()),
+            StarmapLayout::Random
+        );
+    }
+
+    #[test]
+    fn planets_are_placed_on_the_starmap() {
+        let ratios = [0.0, 0.25, 0.5, 0.75, 0.999];
+        for layout in StarmapLayout::ALL.iter() {
+            for count in 1..=MAX_PLANETS {
+                for id in 0..count {
+                    for x_ratio in ratios {
+                        for y_ratio in ratios {
+                            let (x, y) = layout.place(id, count, x_ratio, y_ratio);
+                            assert!(
+                                (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y),
+                                "{:?} placed planet {} of {} at ({}, {})",
+                                layout,
+                                id,
+                                count,
+                                x,
+                                y
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn planets_keep_their_distances() {
+        let rules = RuleSet::default();
+        for layout in StarmapLayout::ALL.iter() {
+            for planets_count in 2..=MAX_PLANETS {
+                let game = SimGame::from_setup(GameSetup {
+                    seed: planets_count as u64,
+                    players_count: 2,
+                    planets_count,
+                    difficulty: SimGame::DIFFICULTY,
+                    ai_difficulties: vec![],
+                    ai_personalities: vec![],
+                    demo: true,
+                    rules,
+                    layout: *layout,
+                });
+                let planets = game.get_game_state().get_starmap().get_planets();
+                assert_eq!(planets.len(), planets_count);
+                for (index, planet1) in planets.iter().enumerate() {
+                    for planet2 in planets.iter().skip(index + 1) {
+                        let distance = SimStarmap::get_distance_between(planet1, planet2);
+                        assert!(
+                            distance > rules.planet_distance_min
+                                && distance < rules.planet_distance_max,
+                            "{:?} placed planets {} apart",
+                            layout,
+                            distance
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
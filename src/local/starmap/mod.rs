@@ -1,6 +1,8 @@
 pub mod builder;
+pub mod layout;
 
 use super::model::CelestialProperties;
+use super::snapshot::PlanetSnapshot;
 use builder::StarmapBuilder;
 
 pub trait Starmap {
@@ -21,6 +23,8 @@ pub trait Starmap {
 
     fn destroy(&self);
 
+    fn snapshot(&self) -> Vec<PlanetSnapshot>;
+
     fn get_distance_between(planet1: &Self::CelestialType, planet2: &Self::CelestialType) -> f32
     where
         Self: Sized;
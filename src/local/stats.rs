@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs;
+
+use super::event::GameEvent;
+use super::player::Player;
+use super::starmap::Starmap;
+use super::{GameState, EXTRACTION_INTERVAL};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsError {
+    Io(String),
+    Format(String),
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct PlayerSample {
+    pub time: f64,
+    pub ships: usize,
+    pub planets: usize,
+    pub resources_extracted: f32,
+    pub resources_spent: f32,
+    pub ships_built: usize,
+    pub ships_lost: usize,
+    pub battles: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerTimeline {
+    pub player_id: usize,
+    pub bot: bool,
+    pub samples: Vec<PlayerSample>,
+}
+
+impl PlayerTimeline {
+    pub fn last_sample(&self) -> PlayerSample {
+        self.samples.last().copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub interval: f64,
+    pub duration: f64,
+    pub winner: Option<usize>,
+    pub players: Vec<PlayerTimeline>,
+}
+
+impl StatsReport {
+    pub fn to_json(&self) -> Result<String, StatsError> {
+        serde_json::to_string_pretty(self).map_err(|e| StatsError::Format(e.to_string()))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), StatsError> {
+        fs::write(path, self.to_json()?).map_err(|e| StatsError::Io(e.to_string()))
+    }
+}
+
+/// Samples the standing of every player at a fixed interval of game time. Ships
+/// and planets are counted from the game state while the running totals (ships
+/// built and lost, resources spent) come from the game events fed by `record`.
+/// Resources extracted are the depletion of the planets, credited to their owner,
+/// and a battle is counted each time a player starts fighting on a planet. Totals
+/// are kept by player id, since the ids of the players need not be their index.
+pub struct StatsCollector {
+    interval: f64,
+    next_sample_time: f64,
+    ship_cost: f32,
+    planet_resources: Vec<f32>,
+    fighting: Vec<(usize, usize)>,
+    casualties: Vec<(usize, usize)>,
+    totals: HashMap<usize, PlayerSample>,
+    timelines: Vec<PlayerTimeline>,
+    winner: Option<usize>,
+}
+
+impl Default for StatsCollector {
+    fn default() -> Self {
+        StatsCollector::new(EXTRACTION_INTERVAL)
+    }
+}
+
+impl StatsCollector {
+    pub fn new(interval: f64) -> Self {
+        StatsCollector {
+            interval,
+            next_sample_time: 0.0,
+            ship_cost: 0.0,
+            planet_resources: vec![],
+            fighting: vec![],
+            casualties: vec![],
+            totals: HashMap::new(),
+            timelines: vec![],
+            winner: None,
+        }
+    }
+
+    pub fn start<T, U>(&mut self, game_state: &GameState<T, U>)
+    where
+        T: Starmap,
+        U: Player,
+    {
+        let starmap = game_state.get_starmap();
+        self.ship_cost = game_state.get_rules().add_ship_resource_cost;
+        self.planet_resources = (0..starmap.get_planets().len())
+            .map(|planet_id| starmap.get_planet_properties(planet_id).resources)
+            .collect();
+        self.fighting.clear();
+        self.casualties.clear();
+        self.totals = game_state
+            .get_players()
+            .iter()
+            .map(|player| (player.properties().id, PlayerSample::default()))
+            .collect();
+        self.timelines = game_state
+            .get_players()
+            .iter()
+            .map(|player| PlayerTimeline {
+                player_id: player.properties().id,
+                bot: player.properties().bot,
+                samples: vec![],
+            })
+            .collect();
+        self.winner = None;
+        self.next_sample_time = game_state.get_time();
+        self.update(game_state);
+    }
+
+    pub fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::ShipBuilt(ship) => {
+                if let Some(totals) = self.totals.get_mut(&ship.contender_id) {
+                    totals.ships_built += 1;
+                    totals.resources_spent += self.ship_cost;
+                }
+            }
+            GameEvent::ShipDestroyed(ship) => {
+                if let Some(totals) = self.totals.get_mut(&ship.contender_id) {
+                    totals.ships_lost += 1;
+                }
+                self.casualties.push((ship.celestial_id, ship.contender_id));
+            }
+            GameEvent::GameWon(player_id) => self.winner = Some(*player_id),
+            _ => (),
+        }
+    }
+
+    pub fn update<T, U>(&mut self, game_state: &GameState<T, U>)
+    where
+        T: Starmap,
+        U: Player,
+    {
+        let time = game_state.get_time();
+        if time < self.next_sample_time {
+            return;
+        }
+        self.next_sample_time = time + self.interval;
+        self.sample(game_state);
+    }
+
+    /// Takes a last sample at the current time and returns the timelines.
+    pub fn finish<T, U>(&mut self, game_state: &GameState<T, U>) -> StatsReport
+    where
+        T: Starmap,
+        U: Player,
+    {
+        let time = game_state.get_time();
+        let is_sampled = self
+            .timelines
+            .iter()
+            .any(|timeline| timeline.last_sample().time >= time);
+        if !is_sampled {
+            self.sample(game_state);
+        }
+        self.report(time)
+    }
+
+    pub fn report(&self, duration: f64) -> StatsReport {
+        StatsReport {
+            interval: self.interval,
+            duration,
+            winner: self.winner,
+            players: self.timelines.clone(),
+        }
+    }
+
+    fn sample<T, U>(&mut self, game_state: &GameState<T, U>)
+    where
+        T: Starmap,
+        U: Player,
+    {
+        let starmap = game_state.get_starmap();
+        let mut fighting = vec![];
+        let mut ships: HashMap<usize, usize> = self.totals.keys().map(|id| (*id, 0)).collect();
+        let mut planets = ships.clone();
+        for (planet_id, resources) in self.planet_resources.iter_mut().enumerate() {
+            let planet_props = starmap.get_planet_properties(planet_id);
+            if let Some(totals) = self.totals.get_mut(&planet_props.contender_id) {
+                totals.resources_extracted += (*resources - planet_props.resources).max(0.0);
+            }
+            *resources = planet_props.resources;
+            if let Some(count) = planets.get_mut(&planet_props.contender_id) {
+                *count += 1;
+            }
+
+            let ships_by_player = game_state.get_ships_by_player_on_planet(planet_props);
+            let present: Vec<usize> = ships_by_player
+                .iter()
+                .filter(|(_, vessels)| !vessels.is_empty())
+                .map(|(player, _)| player.id)
+                .collect();
+            for (player, vessels) in ships_by_player.iter() {
+                if let Some(count) = ships.get_mut(&player.id) {
+                    *count += vessels.len();
+                }
+            }
+            let is_contested = present
+                .iter()
+                .any(|player_id| *player_id != planet_props.contender_id);
+            if is_contested && present.len() > 1 {
+                fighting.extend(present.iter().map(|player_id| (planet_id, *player_id)));
+            }
+        }
+        for fleet in game_state.get_fleets() {
+            if let Some(count) = ships.get_mut(&fleet.contender_id) {
+                *count += fleet.ships.len();
+            }
+        }
+
+        fighting.append(&mut self.casualties);
+        fighting.sort_unstable();
+        fighting.dedup();
+        for (planet_id, player_id) in fighting.iter() {
+            if !self.fighting.contains(&(*planet_id, *player_id)) {
+                if let Some(totals) = self.totals.get_mut(player_id) {
+                    totals.battles += 1;
+                }
+            }
+        }
+        self.fighting = fighting;
+
+        let time = game_state.get_time();
+        for timeline in self.timelines.iter_mut() {
+            let player_id = timeline.player_id;
+            if let Some(totals) = self.totals.get(&player_id) {
+                timeline.samples.push(PlayerSample {
+                    time,
+                    ships: ships[&player_id],
+                    planets: planets[&player_id],
+                    ..*totals
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::clock::SimClock;
+    use crate::local::model::VesselProperties;
+    use crate::local::sim::SimGame;
+
+    fn ship(contender_id: usize) -> VesselProperties {
+        VesselProperties {
+            id: 0,
+            contender_id,
+            celestial_id: 0,
+        }
+    }
+
+    #[test]
+    fn players_start_with_a_planet_and_a_ship() {
+        let game = SimGame::demo(3, 5, 1);
+        let mut stats = StatsCollector::default();
+        stats.start(game.get_game_state());
+
+        let report = stats.report(0.0);
+        assert_eq!(report.players.len(), 3);
+        for timeline in report.players.iter() {
+            let sample = timeline.last_sample();
+            assert_eq!((sample.ships, sample.planets), (1, 1));
+        }
+    }
+
+    #[test]
+    fn events_are_credited_to_their_player() {
+        let mut game = SimGame::demo(3, 5, 1);
+        let ship_cost = game.get_game_state().get_rules().add_ship_resource_cost;
+        let mut stats = StatsCollector::default();
+        stats.start(game.get_game_state());
+
+        stats.record(&GameEvent::ShipBuilt(ship(2)));
+        stats.record(&GameEvent::ShipBuilt(ship(2)));
+        stats.record(&GameEvent::ShipDestroyed(ship(1)));
+        stats.record(&GameEvent::ShipBuilt(ship(usize::MAX)));
+        stats.record(&GameEvent::GameWon(2));
+        game.tick(SimClock::TICK_DURATION);
+        let report = stats.finish(game.get_game_state());
+
+        assert_eq!(report.winner, Some(2));
+        for timeline in report.players.iter() {
+            let sample = timeline.last_sample();
+            let (built, lost) = match timeline.player_id {
+                1 => (0, 1),
+                2 => (2, 0),
+                _ => (0, 0),
+            };
+            assert_eq!((sample.ships_built, sample.ships_lost), (built, lost));
+            assert_eq!(sample.resources_spent, built as f32 * ship_cost);
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::ai::personality::{AiPersonality, PersonalityProfile};
+use super::ai::SearchMode;
+use super::clock::SimClock;
+use super::model::*;
+use super::player::MAX_PLAYERS;
+use super::replay::GameSetup;
+use super::rng::StreamRng;
+use super::ruleset::RuleSet;
+use super::sim::SimGame;
+use super::starmap::layout::StarmapLayout;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TournamentError {
+    Io(String),
+    Format(String),
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    pub name: String,
+    pub difficulty: usize,
+    pub personality: AiPersonality,
+    pub playouts: Option<u64>,
+    pub rollout_horizon: Option<f64>,
+    pub search_mode: Option<SearchMode>,
+    pub verified_transpositions: Option<bool>,
+    pub sight_range: Option<f32>,
+    pub weights: Option<PersonalityProfile>,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        AiConfig {
+            name: String::new(),
+            difficulty: 2,
+            personality: AiPersonality::Balanced,
+            playouts: None,
+            rollout_horizon: None,
+            search_mode: None,
+            verified_transpositions: None,
+            sight_range: None,
+            weights: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TournamentConfig {
+    pub matches: usize,
+    pub seed: u64,
+    pub players_count: usize,
+    pub planets_count: usize,
+    pub time_limit: f64,
+    pub threads: usize,
+    pub rules: RuleSet,
+    pub layout: StarmapLayout,
+    pub ais: Vec<AiConfig>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig {
+            matches: 100,
+            seed: 1,
+            players_count: 4,
+            planets_count: 12,
+            time_limit: 600.0,
+            threads: 0,
+            rules: RuleSet::default(),
+            layout: StarmapLayout::default(),
+            ais: AiPersonality::ALL
+                .iter()
+                .map(|personality| AiConfig {
+                    name: format!("{personality:?}").to_lowercase(),
+                    personality: *personality,
+                    ..AiConfig::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TournamentConfig {
+    pub fn from_json(json: &str) -> Result<Self, TournamentError> {
+        let config: TournamentConfig =
+            serde_json::from_str(json).map_err(|e| TournamentError::Format(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Self, TournamentError> {
+        let json = fs::read_to_string(path).map_err(|e| TournamentError::Io(e.to_string()))?;
+        TournamentConfig::from_json(&json)
+    }
+
+    pub fn validate(&self) -> Result<(), TournamentError> {
+        if self.ais.is_empty() {
+            return Err(TournamentError::Invalid(
+                "ais must list at least one configuration".to_string(),
+            ));
+        }
+        if self.players_count < 2 || self.players_count > MAX_PLAYERS {
+            return Err(TournamentError::Invalid(format!(
+                "players_count must be between 2 and {MAX_PLAYERS}, got {}",
+                self.players_count
+            )));
+        }
+        if self.planets_count < self.players_count {
+            return Err(TournamentError::Invalid(format!(
+                "planets_count must be at least players_count, got {}",
+                self.planets_count
+            )));
+        }
+        if !self.time_limit.is_finite() || self.time_limit <= 0.0 {
+            return Err(TournamentError::Invalid(format!(
+                "time_limit must be a positive number, got {}",
+                self.time_limit
+            )));
+        }
+        self.rules
+            .validate()
+            .map_err(|e| TournamentError::Invalid(format!("{e:?}")))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub seed: u64,
+    pub seats: Vec<usize>,
+    pub winner: Option<usize>,
+    pub length: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiStanding {
+    pub name: String,
+    pub matches: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub win_rate: f64,
+    pub average_length: f64,
+    pub rating: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentReport {
+    pub matches: usize,
+    pub draws: usize,
+    pub average_length: f64,
+    pub standings: Vec<AiStanding>,
+    pub results: Vec<MatchResult>,
+}
+
+impl TournamentReport {
+    pub fn to_json(&self) -> Result<String, TournamentError> {
+        serde_json::to_string_pretty(self).map_err(|e| TournamentError::Format(e.to_string()))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "name,matches,wins,draws,win_rate,average_length,rating\n".to_string();
+        for standing in &self.standings {
+            csv.push_str(&format!(
+                "{},{},{},{},{:.4},{:.2},{:.1}\n",
+                standing.name,
+                standing.matches,
+                standing.wins,
+                standing.draws,
+                standing.win_rate,
+                standing.average_length,
+                standing.rating
+            ));
+        }
+        csv
+    }
+}
+
+/// Plays seeded demo games between AI configurations and rates them. Every match
+/// runs on its own starmap, seeding its players from a shuffle of the configurations.
+pub struct Tournament {
+    config: TournamentConfig,
+}
+
+impl Tournament {
+    pub const INITIAL_RATING: f64 = 1500.0;
+    pub const K_FACTOR: f64 = 32.0;
+
+    pub fn new(config: TournamentConfig) -> Self {
+        Tournament { config }
+    }
+
+    pub fn play_match(&self, index: usize) -> MatchResult {
+        let config = &self.config;
+        let seed = config.seed.wrapping_add(index as u64);
+        let mut rng = StreamRng::seed_from_u64(seed);
+        let mut order: Vec<usize> = (0..config.ais.len()).collect();
+        order.shuffle(&mut rng);
+        let seats: Vec<usize> = (0..config.players_count)
+            .map(|seat| order[seat % order.len()])
+            .collect();
+
+        let mut game = SimGame::from_setup(GameSetup {
+            seed,
+            players_count: config.players_count,
+            planets_count: config.planets_count,
+            difficulty: SimGame::DIFFICULTY,
+            ai_difficulties: seats
+                .iter()
+                .map(|seat| config.ais[*seat].difficulty)
+                .collect(),
+            ai_personalities: seats
+                .iter()
+                .map(|seat| config.ais[*seat].personality)
+                .collect(),
+            demo: true,
+            rules: config.rules,
+            layout: config.layout,
+        });
+        let mut player_ids = vec![];
+        for (ai, seat) in game
+            .get_game_state_mut()
+            .get_ais_mut()
+            .iter_mut()
+            .zip(seats.iter())
+        {
+            let ai_config = &config.ais[*seat];
+            let mut difficulty_profile = ai.get_difficulty_profile();
+            if let Some(playouts) = ai_config.playouts {
+                difficulty_profile.playouts = playouts;
+            }
+            if let Some(rollout_horizon) = ai_config.rollout_horizon {
+                difficulty_profile.rollout_horizon = rollout_horizon;
+            }
+            if let Some(search_mode) = ai_config.search_mode {
+                difficulty_profile.search_mode = search_mode;
+            }
+            if let Some(verified_transpositions) = ai_config.verified_transpositions {
+                difficulty_profile.verified_transpositions = verified_transpositions;
+            }
+            if ai_config.sight_range.is_some() {
+                difficulty_profile.sight_range = ai_config.sight_range;
+            }
+            ai.set_difficulty_profile(difficulty_profile);
+            if let Some(weights) = ai_config.weights {
+                ai.set_personality_profile(weights);
+            }
+            player_ids.push(ai.get_player().id);
+        }
+
+        let winner = game
+            .run(SimClock::TICK_DURATION, config.time_limit)
+            .and_then(|winner| {
+                player_ids
+                    .iter()
+                    .position(|id| *id == winner.properties().id)
+            })
+            .map(|seat| seats[seat]);
+        MatchResult {
+            seed,
+            seats,
+            winner,
+            length: game.get_game_state().get_time(),
+        }
+    }
+
+    pub fn run(&self) -> TournamentReport {
+        let threads_count = if self.config.threads > 0 {
+            self.config.threads
+        } else {
+            thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(1)
+        };
+        let next_match = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; self.config.matches]);
+        thread::scope(|scope| {
+            for _ in 0..threads_count.min(self.config.matches).max(1) {
+                scope.spawn(|| loop {
+                    let index = next_match.fetch_add(1, Ordering::SeqCst);
+                    if index >= self.config.matches {
+                        return;
+                    }
+                    let result = self.play_match(index);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+        let results: Vec<MatchResult> = results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        self.report(results)
+    }
+
+    fn report(&self, results: Vec<MatchResult>) -> TournamentReport {
+        let ais_count = self.config.ais.len();
+        let mut ratings = vec![Self::INITIAL_RATING; ais_count];
+        let mut matches = vec![0; ais_count];
+        let mut wins = vec![0; ais_count];
+        let mut draws = vec![0; ais_count];
+        let mut lengths = vec![0.0; ais_count];
+        for result in &results {
+            let mut participants = result.seats.clone();
+            participants.sort_unstable();
+            participants.dedup();
+            for participant in &participants {
+                matches[*participant] += 1;
+                lengths[*participant] += result.length;
+                match result.winner {
+                    Some(winner) if winner == *participant => wins[*participant] += 1,
+                    None => draws[*participant] += 1,
+                    _ => (),
+                }
+            }
+            Self::update_ratings(&mut ratings, &participants, result.winner);
+        }
+
+        let standings = self
+            .config
+            .ais
+            .iter()
+            .enumerate()
+            .map(|(index, ai)| AiStanding {
+                name: ai.name.clone(),
+                matches: matches[index],
+                wins: wins[index],
+                draws: draws[index],
+                win_rate: wins[index] as f64 / matches[index].max(1) as f64,
+                average_length: lengths[index] / matches[index].max(1) as f64,
+                rating: ratings[index],
+            })
+            .collect();
+        TournamentReport {
+            matches: results.len(),
+            draws: results.iter().filter(|r| r.winner.is_none()).count(),
+            average_length: results.iter().map(|r| r.length).sum::<f64>()
+                / results.len().max(1) as f64,
+            standings,
+            results,
+        }
+    }
+
+    /// Rates a free-for-all as the pairwise games between its participants: the
+    /// winner beats every other one, while a game without winner draws every pair.
+    fn update_ratings(ratings: &mut [f64], participants: &[usize], winner: Option<usize>) {
+        if participants.len() < 2 {
+            return;
+        }
+        let k_factor = Self::K_FACTOR / (participants.len() - 1) as f64;
+        let mut deltas = vec![0.0; ratings.len()];
+        for (index, first) in participants.iter().enumerate() {
+            for second in participants.iter().skip(index + 1) {
+                let score = match winner {
+                    Some(winner) if winner == *first => 1.0,
+                    Some(winner) if winner == *second => 0.0,
+                    Some(_) => continue,
+                    None => 0.5,
+                };
+                let expected =
+                    1.0 / (1.0 + 10f64.powf((ratings[*second] - ratings[*first]) / 400.0));
+                deltas[*first] += k_factor * (score - expected);
+                deltas[*second] -= k_factor * (score - expected);
+            }
+        }
+        ratings
+            .iter_mut()
+            .zip(deltas)
+            .for_each(|(rating, delta)| *rating += delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_count_is_bounded() {
+        for players_count in [1, MAX_PLAYERS + 1] {
+            let config = TournamentConfig {
+                players_count,
+                planets_count: MAX_PLAYERS + 1,
+                ..TournamentConfig::default()
+            };
+            assert!(matches!(
+                config.validate(),
+                Err(TournamentError::Invalid(_))
+            ));
+        }
+        let config = TournamentConfig {
+            players_count: MAX_PLAYERS,
+            planets_count: MAX_PLAYERS,
+            ..TournamentConfig::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn winner_of_equal_ratings_takes_half_the_k_factor() {
+        let mut ratings = [Tournament::INITIAL_RATING; 2];
+        Tournament::update_ratings(&mut ratings, &[0, 1], Some(1));
+        assert_eq!(
+            ratings,
+            [
+                Tournament::INITIAL_RATING - Tournament::K_FACTOR / 2.0,
+                Tournament::INITIAL_RATING + Tournament::K_FACTOR / 2.0
+            ]
+        );
+    }
+
+    #[test]
+    fn k_factor_is_split_across_pairs() {
+        let mut ratings = [Tournament::INITIAL_RATING; 3];
+        Tournament::update_ratings(&mut ratings, &[0, 1, 2], Some(0));
+        let delta = Tournament::K_FACTOR / 2.0 / 2.0;
+        assert_eq!(
+            ratings,
+            [
+                Tournament::INITIAL_RATING + 2.0 * delta,
+                Tournament::INITIAL_RATING - delta,
+                Tournament::INITIAL_RATING - delta
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_leaves_equal_ratings_unchanged() {
+        let mut ratings = [Tournament::INITIAL_RATING; 3];
+        Tournament::update_ratings(&mut ratings, &[0, 1, 2], None);
+        assert_eq!(ratings, [Tournament::INITIAL_RATING; 3]);
+    }
+
+    #[test]
+    fn seeded_tournament_gives_same_report() {
+        let config = TournamentConfig {
+            matches: 2,
+            seed: 3,
+            players_count: 2,
+            planets_count: 3,
+            threads: 2,
+            ais: TournamentConfig::default()
+                .ais
+                .into_iter()
+                .take(2)
+                .collect(),
+            ..TournamentConfig::default()
+        };
+        let first = Tournament::new(config.clone()).run();
+        let second = Tournament::new(config).run();
+
+        assert_eq!(first.matches, 2);
+        assert_eq!(first.to_json().unwrap(), second.to_json().unwrap());
+    }
+}
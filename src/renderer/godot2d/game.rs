@@ -7,34 +7,100 @@ use super::input::InputHandler2D;
 use super::player::Player2D;
 use super::starmap::Starmap2D;
 use super::*;
+use crate::local::ai::personality::AiPersonality;
+use crate::local::event::GameEvent;
 use crate::local::model::*;
+use crate::local::player::*;
+use crate::local::replay::*;
+use crate::local::rules::*;
+use crate::local::ruleset::RuleSet;
+use crate::local::snapshot::GameSnapshot;
+use crate::local::starmap::layout::StarmapLayout;
 use crate::local::starmap::*;
 use crate::local::GameState;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Game {
     players_count: usize,
     planets_count: usize,
     difficulty: usize,
+    ai_difficulties: Vec<usize>,
+    ai_personalities: Vec<AiPersonality>,
     demo: bool,
+    seed: u64,
+    rules: RuleSet,
+    layout: StarmapLayout,
 }
 
 impl Game {
-    pub fn demo() -> Self {
+    pub fn demo(seed: u64) -> Self {
         Game {
             planets_count: 15,
             demo: true,
             players_count: 10,
             difficulty: 2,
+            ai_difficulties: vec![],
+            ai_personalities: vec![],
+            seed,
+            rules: RuleSet::default(),
+            layout: StarmapLayout::default(),
         }
     }
 
-    pub fn new(ais_count: usize, planets_count: usize, difficulty: usize) -> Self {
+    pub fn new(ais_count: usize, planets_count: usize, difficulty: usize, seed: u64) -> Self {
         Game {
             planets_count,
             demo: false,
             players_count: ais_count + 1,
             difficulty,
+            ai_difficulties: vec![],
+            ai_personalities: vec![],
+            seed,
+            rules: RuleSet::default(),
+            layout: StarmapLayout::default(),
+        }
+    }
+
+    pub fn with_rules(self, rules: RuleSet) -> Self {
+        Game { rules, ..self }
+    }
+
+    pub fn with_layout(self, layout: StarmapLayout) -> Self {
+        Game { layout, ..self }
+    }
+
+    pub fn with_ai_difficulties(self, ai_difficulties: Vec<usize>) -> Self {
+        Game {
+            ai_difficulties,
+            ..self
+        }
+    }
+
+    pub fn from_setup(setup: GameSetup) -> Self {
+        Game {
+            planets_count: setup.planets_count,
+            demo: setup.demo,
+            players_count: setup.players_count,
+            difficulty: setup.difficulty,
+            ai_difficulties: setup.ai_difficulties,
+            ai_personalities: setup.ai_personalities,
+            seed: setup.seed,
+            rules: setup.rules,
+            layout: setup.layout,
+        }
+    }
+
+    pub fn get_setup(&self) -> GameSetup {
+        GameSetup {
+            seed: self.seed,
+            players_count: self.players_count,
+            planets_count: self.planets_count,
+            difficulty: self.difficulty,
+            ai_difficulties: self.ai_difficulties.clone(),
+            ai_personalities: self.ai_personalities.clone(),
+            demo: self.demo,
+            rules: self.rules,
+            layout: self.layout,
         }
     }
 
@@ -62,20 +128,31 @@ impl Game {
         F: FnMut() -> RefPlanetNode2D,
     {
         game_state.borrow_mut().reset();
-        let input_handler = Rc::new(RefCell::new(InputHandler2D::new()));
+        game_state.borrow_mut().set_seed(self.seed);
+        game_state.borrow_mut().set_rules(self.rules);
+        let setup = self.get_setup();
+        game_state
+            .borrow_mut()
+            .set_difficulty(setup.get_difficulty());
+        game_state
+            .borrow_mut()
+            .set_ai_difficulties(setup.get_ai_difficulties());
+        game_state
+            .borrow_mut()
+            .set_ai_personalities(setup.ai_personalities.clone());
+        game_state.borrow_mut().start_recording(setup);
+        let input_handler = Rc::new(RefCell::new(InputHandler2D::new(
+            self.rules.move_ship_fleet_percent,
+        )));
         let mut starmap = Starmap2D::new(self.planets_count)
             .with_generator(|id| {
                 let planet_node = planet_create_fn();
                 Planet::with_mut(&planet_node, |planet| {
                     planet.set_game_state(game_state.clone());
-                    planet.set_random_features();
                     planet.set_id(id);
+                    planet.set_random_features(self.layout, self.planets_count);
                     planet.set_input_handler(input_handler.clone(), |planet, player_action| {
-                        let game_state = planet.get_game_state();
-                        if let Some(current_player) = game_state.get_current_player() {
-                            let planets = game_state.get_starmap().get_planets();
-                            Game::perform_action(&planets, current_player, player_action);
-                        }
+                        Game::on_planet_input(*planet, player_action)
                     });
                 });
 
@@ -83,7 +160,8 @@ impl Game {
             })
             .with_validator(|planet1, planet2| {
                 let distance = Starmap2D::get_distance_between(planet1, planet2);
-                distance > 100.0 && distance < 2000.0
+                distance > self.rules.planet_distance_min
+                    && distance < self.rules.planet_distance_max
             })
             .with_cleaner(|planet| unsafe { planet.assume_safe().queue_free() })
             .build();
@@ -95,8 +173,8 @@ impl Game {
             .for_each(|(index, planet_node)| {
                 Planet::with_mut(planet_node, |planet| {
                     planet.set_resources(
-                        Consts::ADD_PLAYER_RESOURCES_INIT,
-                        Consts::ADD_PLAYER_RESOURCES_INC,
+                        self.rules.add_player_resources_init,
+                        self.rules.add_player_resources_inc,
                     );
                     planet.add_player(index > 0 || self.demo);
                 });
@@ -106,27 +184,122 @@ impl Game {
         game_state.set_starmap(starmap);
     }
 
+    pub fn restore<F>(
+        &self,
+        game_state: Rc<RefCell<GameState<Starmap2D, Player2D>>>,
+        snapshot: &GameSnapshot,
+        mut planet_create_fn: F,
+    ) where
+        F: FnMut() -> RefPlanetNode2D,
+    {
+        game_state.borrow_mut().reset();
+        game_state.borrow_mut().set_rules(snapshot.setup.rules);
+        let input_handler = Rc::new(RefCell::new(InputHandler2D::new(
+            snapshot.setup.rules.move_ship_fleet_percent,
+        )));
+        let planets: Vec<RefPlanetNode2D> = snapshot
+            .planets
+            .iter()
+            .map(|planet_snapshot| {
+                let planet_node = planet_create_fn();
+                Planet::with_mut(&planet_node, |planet| {
+                    planet.set_game_state(game_state.clone());
+                    planet.restore_features(planet_snapshot);
+                    planet.set_input_handler(input_handler.clone(), |planet, player_action| {
+                        Game::on_planet_input(*planet, player_action)
+                    });
+                });
+                planet_node
+            })
+            .collect();
+
+        let players = snapshot
+            .players
+            .iter()
+            .map(|player_snapshot| {
+                let player_planets = player_snapshot
+                    .planets
+                    .iter()
+                    .map(|planet_id| planets[*planet_id])
+                    .collect();
+                let ships = player_snapshot
+                    .ships
+                    .iter()
+                    .map(|ship_props| {
+                        let planet_id = if ship_props.celestial_id != usize::MAX {
+                            ship_props.celestial_id
+                        } else {
+                            snapshot
+                                .fleets
+                                .iter()
+                                .find(|fleet| fleet.ships.iter().any(|s| s.id == ship_props.id))
+                                .map(|fleet| fleet.origin_id)
+                                .expect("travelling ships are checked to be in a fleet")
+                        };
+                        Planet::with(&planets[planet_id], |planet| {
+                            planet.restore_ship(player_snapshot.properties, *ship_props)
+                        })
+                    })
+                    .collect();
+                Rc::new(Player2D::restore(
+                    player_snapshot.properties,
+                    player_planets,
+                    ships,
+                ))
+            })
+            .collect();
+
+        let mut starmap = Starmap2D::default();
+        starmap.set_planets(planets);
+
+        let mut game_state = game_state.borrow_mut();
+        game_state.restore(snapshot, players);
+        game_state.set_starmap(starmap);
+    }
+
+    fn on_planet_input(planet: &Planet, player_action: PlayerAction) {
+        let game_state = planet.get_game_state();
+        if game_state.is_replaying() {
+            return;
+        }
+        if let Some(current_player) = game_state.get_current_player() {
+            // Rejected actions are reported by a GameEvent::ActionRejected.
+            let _ = Game::perform_action(&game_state, current_player, player_action);
+        }
+    }
+
     pub fn perform_action(
-        planets: &[RefPlanetNode2D],
+        game_state: &GameState<Starmap2D, Player2D>,
         player: &Player2D,
         player_action: PlayerAction,
-    ) {
+    ) -> Result<ActionOutcome, ActionError> {
+        let outcome = match validate_action(game_state, player.properties().id, player_action) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                game_state.emit_event(GameEvent::ActionRejected(player.properties().id, err));
+                return Err(err);
+            }
+        };
+        game_state.record_action(player.properties().id, player_action);
+        let rules = game_state.get_rules();
+        let planets = game_state.get_starmap().get_planets();
         match player_action {
             PlayerAction::AddShip(on) => {
-                let planet_on = Planet::get_by_id(planets, on.id);
+                let planet_on = Planet::get_by_id(&planets, on.id);
                 Planet::with(planet_on, |planet| {
-                    planet.add_ship(Consts::ADD_SHIP_RESOURCE_COST, player)
+                    planet.add_ship(rules.add_ship_resource_cost, player)
                 });
             }
-            PlayerAction::MoveShips(from, to) => {
-                let planet_from = Planet::get_by_id(planets, from.id);
-                let planet_to = Planet::get_by_id(planets, to.id);
+            PlayerAction::MoveShips(from, to, size) => {
+                let planet_from = Planet::get_by_id(&planets, from.id);
+                let planet_to = Planet::get_by_id(&planets, to.id);
 
                 Planet::with(planet_from, |planet| {
-                    planet.move_ships(Consts::MOVE_SHIP_FLEET_PERCENT, player, planet_to);
+                    planet.move_ships(size, player, planet_to);
                 });
             }
             _ => (),
         }
+        Ok(outcome)
     }
 }
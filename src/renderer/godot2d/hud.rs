@@ -1,5 +1,10 @@
 use gdnative::prelude::*;
-use gdnative_bindings::{AudioStreamPlayer, HSlider};
+use gdnative_bindings::{AudioStreamPlayer, HSlider, Line2D, OptionButton, Timer};
+
+use crate::local::player::get_color;
+use crate::local::rules::ActionError;
+use crate::local::starmap::layout::StarmapLayout;
+use crate::local::stats::{PlayerSample, StatsReport};
 
 pub type RefHUDNode = Ref<Node2D>;
 
@@ -12,6 +17,12 @@ pub struct HUD {
 
 #[methods]
 impl HUD {
+    const GRAPH_WIDTH: f32 = 230.0;
+    const GRAPH_HEIGHT: f32 = 32.0;
+    const GRAPH_MARGIN: f32 = 24.0;
+    const GRAPH_ROW_HEIGHT: f32 = 54.0;
+    const GRAPH_COLUMNS: usize = 4;
+
     fn new(owner: &Node2D) -> Self {
         let owner = unsafe { owner.assume_unique() }.cast::<Node2D>().unwrap();
         HUD {
@@ -35,6 +46,15 @@ impl HUD {
         };
         planets_slider.set_value(10.0);
 
+        let layout_option = unsafe {
+            owner
+                .get_node_as::<OptionButton>("LayoutOption")
+                .expect("Cannot resolve LayoutOption")
+        };
+        for (index, layout) in StarmapLayout::ALL.iter().enumerate() {
+            layout_option.add_item(layout.get_name(), index as i64);
+        }
+
         let bgm_menu = unsafe {
             owner
                 .get_node_as::<AudioStreamPlayer>("BgmMenu")
@@ -86,6 +106,11 @@ impl HUD {
                 .get_node_as::<HSlider>("DifficultySlider")
                 .expect("Cannot resolve DifficultySlider")
         };
+        let layout_option = unsafe {
+            owner
+                .get_node_as::<OptionButton>("LayoutOption")
+                .expect("Cannot resolve LayoutOption")
+        };
 
         let bgm_menu = unsafe {
             owner
@@ -101,6 +126,12 @@ impl HUD {
         };
         bgm.play(0.0);
 
+        let stats_node = unsafe {
+            owner
+                .get_node_as::<Node2D>("Stats")
+                .expect("Cannot resolve Stats")
+        };
+        stats_node.hide();
         owner.hide();
 
         let root_node = unsafe { owner.get_parent().unwrap().assume_safe() }.as_ref();
@@ -111,13 +142,52 @@ impl HUD {
                 Variant::new(planets_slider.value() as u64),
                 Variant::new(difficulty_slider.value() as u64),
                 Variant::new(false),
+                Variant::new(0u64),
+                Variant::new(VariantArray::new_shared()),
+                Variant::new(layout_option.selected().max(0) as u64),
             ],
         );
     }
 
-    pub fn game_over(&self, win: bool) {
+    #[method]
+    pub fn _on_action_error_timeout(&self, #[base] owner: &Node2D) {
+        let action_error_label = unsafe {
+            owner
+                .get_node_as::<Label>("Notice/ActionError")
+                .expect("Cannot resolve Notice/ActionError")
+        };
+        action_error_label.set_text("");
+    }
+
+    /// Tells the player why their last action was not performed, until the timer
+    /// clears the message.
+    pub fn show_action_error(&self, err: ActionError) {
+        let owner = unsafe { self.owner.assume_safe() }.as_ref();
+        let action_error_label = unsafe {
+            owner
+                .get_node_as::<Label>("Notice/ActionError")
+                .expect("Cannot resolve Notice/ActionError")
+        };
+        action_error_label.set_text(match err {
+            ActionError::UnknownPlanet(_) => "This planet does not exist",
+            ActionError::NotOwner(_) => "You do not own this planet",
+            ActionError::InsufficientResources(_) => "Not enough resources to build a ship",
+            ActionError::NoShipsToMove(_) => "No ships to move from this planet",
+            ActionError::SamePlanet(_) => "Ships are already on this planet",
+        });
+
+        let action_error_timer = unsafe {
+            owner
+                .get_node_as::<Timer>("ActionErrorTimer")
+                .expect("Cannot resolve ActionErrorTimer")
+        };
+        action_error_timer.start(-1.0);
+    }
+
+    pub fn game_over(&self, win: bool, stats: &StatsReport) {
         let owner = unsafe { self.owner.assume_safe() }.as_ref();
         owner.show();
+        self.draw_stats(owner, stats);
 
         let title_label = unsafe {
             owner
@@ -145,6 +215,73 @@ impl HUD {
         }
     }
 
+    fn draw_stats(&self, owner: &Node2D, stats: &StatsReport) {
+        let stats_node = unsafe {
+            owner
+                .get_node_as::<Node2D>("Stats")
+                .expect("Cannot resolve Stats")
+        };
+        let graphs = unsafe {
+            stats_node
+                .get_node_as::<Node2D>("Graphs")
+                .expect("Cannot resolve Stats/Graphs")
+        };
+        for index in 0..graphs.get_child_count() {
+            unsafe { graphs.get_child(index).unwrap().assume_safe() }.queue_free();
+        }
+        // In the order of the labels of the Stats node.
+        let values: [fn(&PlayerSample) -> f32; 7] = [
+            |sample| sample.ships as f32,
+            |sample| sample.planets as f32,
+            |sample| sample.ships_built as f32,
+            |sample| sample.ships_lost as f32,
+            |sample| sample.resources_extracted,
+            |sample| sample.resources_spent,
+            |sample| sample.battles as f32,
+        ];
+        for (index, value_fn) in values.iter().enumerate() {
+            let position = Vector2::new(
+                (index % Self::GRAPH_COLUMNS) as f32 * (Self::GRAPH_WIDTH + Self::GRAPH_MARGIN),
+                (index / Self::GRAPH_COLUMNS) as f32 * Self::GRAPH_ROW_HEIGHT,
+            );
+            HUD::draw_graph(&graphs, stats, position, *value_fn);
+        }
+        stats_node.show();
+    }
+
+    /// Plots one line per player, scaled so that the highest value of any player
+    /// reaches the top of the graph at `position`.
+    fn draw_graph<F>(graphs: &Node2D, stats: &StatsReport, position: Vector2, value_fn: F)
+    where
+        F: Fn(&PlayerSample) -> f32,
+    {
+        let max_value = stats
+            .players
+            .iter()
+            .flat_map(|timeline| timeline.samples.iter())
+            .map(&value_fn)
+            .fold(1.0, f32::max);
+        let duration = stats.duration.max(stats.interval) as f32;
+        for timeline in stats.players.iter() {
+            let points: Vec<Vector2> = timeline
+                .samples
+                .iter()
+                .map(|sample| {
+                    position
+                        + Vector2::new(
+                            sample.time as f32 / duration * Self::GRAPH_WIDTH,
+                            Self::GRAPH_HEIGHT * (1.0 - value_fn(sample) / max_value),
+                        )
+                })
+                .collect();
+            let line = Line2D::new();
+            line.set_points(PoolArray::from_vec(points));
+            line.set_width(2.0);
+            line.set_default_color(get_color(timeline.player_id).into());
+            graphs.add_child(line.into_shared(), false);
+        }
+    }
+
     pub fn with_mut<F, T>(base: &RefHUDNode, mut with_fn: F) -> T
     where
         F: FnMut(&mut HUD) -> T,
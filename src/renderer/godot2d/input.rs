@@ -1,67 +1,135 @@
+use gdnative::api::GlobalConstants;
 use gdnative::prelude::*;
 use gdnative_bindings::*;
 
 use std::time::SystemTime;
 
-use crate::local::input::InputHandler;
+use crate::local::input::{InputEvent as LocalInputEvent, InputHandler, MouseButton};
 use crate::local::model::CelestialProperties;
-use crate::local::player::PlayerAction;
+use crate::local::player::{FleetSize, PlayerAction};
+use crate::local::ruleset::RuleSet;
+
+const FLEET_PERCENT_STEP: usize = 10;
 
 pub struct InputHandler2D {
     target_planet: Option<CelestialProperties>,
     primary_mouse_button_time: SystemTime,
+    primary_mouse_button_pressed: bool,
+    fleet_percent: usize,
+    selected_fleet_percent: usize,
 }
 
 impl Default for InputHandler2D {
     fn default() -> Self {
+        InputHandler2D::new(RuleSet::default().move_ship_fleet_percent)
+    }
+}
+
+impl InputHandler<CelestialProperties> for InputHandler2D {
+    fn convert(&mut self, target: CelestialProperties, event: LocalInputEvent) -> PlayerAction {
+        match event {
+            LocalInputEvent::MouseButton {
+                button,
+                pressed,
+                shift,
+                control,
+            } => self.handle_mouse_button_event(target, button, pressed, shift, control),
+        }
+    }
+}
+
+impl InputHandler2D {
+    pub fn new(fleet_percent: usize) -> Self {
         InputHandler2D {
             target_planet: None,
             primary_mouse_button_time: SystemTime::now(),
+            primary_mouse_button_pressed: false,
+            fleet_percent,
+            selected_fleet_percent: fleet_percent,
         }
     }
-}
 
-impl InputHandler<CelestialProperties> for InputHandler2D {
-    fn convert(&mut self, target: CelestialProperties, event: Ref<InputEvent>) -> PlayerAction {
+    pub fn convert_event(event: Ref<InputEvent>) -> Option<LocalInputEvent> {
         let input_event_mouse_button: Option<Ref<InputEventMouseButton>> = event.cast();
         if let Some(event) = input_event_mouse_button {
-            return self.handle_mouse_button_event(target, event);
+            let event = unsafe { event.assume_safe() };
+            let button = match event.button_index() {
+                GlobalConstants::BUTTON_LEFT => MouseButton::Left,
+                GlobalConstants::BUTTON_RIGHT => MouseButton::Right,
+                GlobalConstants::BUTTON_MIDDLE => MouseButton::Middle,
+                GlobalConstants::BUTTON_WHEEL_UP => MouseButton::WheelUp,
+                GlobalConstants::BUTTON_WHEEL_DOWN => MouseButton::WheelDown,
+                _ => return None,
+            };
+            return Some(LocalInputEvent::MouseButton {
+                button,
+                pressed: event.is_pressed(),
+                shift: event.shift(),
+                control: event.control(),
+            });
         }
 
-        PlayerAction::Wait
-    }
-}
-
-impl InputHandler2D {
-    pub fn new() -> Self {
-        InputHandler2D::default()
+        None
     }
 
     fn handle_mouse_button_event(
         &mut self,
         target: CelestialProperties,
-        event: Ref<InputEventMouseButton>,
+        button: MouseButton,
+        pressed: bool,
+        shift: bool,
+        control: bool,
     ) -> PlayerAction {
         let mut player_action = PlayerAction::Wait;
-        let event = unsafe { event.assume_safe() };
-        if event.button_index() == 1 {
-            if event.is_pressed() {
-                self.primary_mouse_button_time = SystemTime::now();
-            } else {
-                let duration = SystemTime::now()
-                    .duration_since(self.primary_mouse_button_time)
-                    .unwrap();
-                if duration.as_millis() < 500 {
-                    player_action = PlayerAction::AddShip(target);
-                } else if self.target_planet.is_some() {
-                    let current = self.target_planet.unwrap();
-                    if current.id != target.id {
-                        player_action = PlayerAction::MoveShips(current, target);
+        match button {
+            MouseButton::Left => {
+                self.primary_mouse_button_pressed = pressed;
+                if pressed {
+                    self.primary_mouse_button_time = SystemTime::now();
+                    self.selected_fleet_percent = self.fleet_percent;
+                } else {
+                    let duration = SystemTime::now()
+                        .duration_since(self.primary_mouse_button_time)
+                        .unwrap();
+                    if duration.as_millis() < 500 {
+                        player_action = PlayerAction::AddShip(target);
+                    } else if self.target_planet.is_some() {
+                        let current = self.target_planet.unwrap();
+                        if current.id != target.id {
+                            player_action = PlayerAction::MoveShips(
+                                current,
+                                target,
+                                self.fleet_size(shift, control),
+                            );
+                        }
                     }
                 }
             }
+            MouseButton::WheelUp | MouseButton::WheelDown
+                if pressed && self.primary_mouse_button_pressed =>
+            {
+                self.selected_fleet_percent = if button == MouseButton::WheelUp {
+                    (self.selected_fleet_percent + FLEET_PERCENT_STEP).min(100)
+                } else {
+                    self.selected_fleet_percent
+                        .saturating_sub(FLEET_PERCENT_STEP)
+                        .max(FLEET_PERCENT_STEP)
+                };
+                return player_action;
+            }
+            _ => (),
         };
         self.target_planet.replace(target);
         player_action
     }
+
+    fn fleet_size(&self, shift: bool, control: bool) -> FleetSize {
+        if control {
+            FleetSize::Percent(100)
+        } else if shift {
+            FleetSize::AllBut(1)
+        } else {
+            FleetSize::Percent(self.selected_fleet_percent)
+        }
+    }
 }
@@ -6,6 +6,7 @@ mod player;
 pub mod ship;
 mod starmap;
 
+use gdnative::api::GlobalConstants;
 use gdnative::object::*;
 use gdnative::prelude::*;
 use gdnative_bindings::*;
@@ -13,15 +14,25 @@ use gdnative_bindings::*;
 use rand::*;
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use self::game::Game;
 use self::hud::HUD;
+use self::planet::{Planet, RefPlanetNode2D};
 use self::player::Player2D;
+use self::ship::Ship;
 use self::starmap::Starmap2D;
+use crate::local::ai::pool::AiWorkerPool;
+use crate::local::event::*;
 use crate::local::model::*;
 use crate::local::player::*;
+use crate::local::replay::*;
+use crate::local::rng::*;
+use crate::local::ruleset::*;
+use crate::local::snapshot::GameSnapshot;
+use crate::local::starmap::layout::StarmapLayout;
 use crate::local::starmap::*;
+use crate::local::stats::StatsCollector;
 use crate::local::GameState;
 use crate::renderer::godot2d::hud::RefHUDNode;
 
@@ -40,21 +51,44 @@ pub struct Main {
     planet: Ref<PackedScene>,
     #[property]
     hud: Ref<PackedScene>,
+    #[property]
+    rules_path: String,
+    #[property]
+    ai_workers: i64,
+    #[property]
+    ai_budget_ms: i64,
 
     game_state: Rc<RefCell<GameState<Starmap2D, Player2D>>>,
     game: Game,
+    rules: RuleSet,
+    autosave_time: f64,
+    events: EventSubscription,
+    stats: StatsCollector,
 
     hud_node: Option<RefHUDNode>,
 }
 
 #[methods]
 impl Main {
+    const AUTOSAVE_PATH: &'static str = "user://autosave.json";
+    const AUTOSAVE_INTERVAL: f64 = 60.0;
+    const STATS_PATH: &'static str = "user://stats.json";
+
     fn new(_owner: &Node) -> Self {
+        let game_state = GameState::new();
+        let events = game_state.subscribe_events();
         Main {
             planet: PackedScene::new().into_shared(),
             hud: PackedScene::new().into_shared(),
-            game_state: Rc::new(RefCell::new(GameState::new())),
-            game: Game::demo(),
+            rules_path: "user://rules.json".to_string(),
+            ai_workers: 0,
+            ai_budget_ms: 100,
+            game_state: Rc::new(RefCell::new(game_state)),
+            game: Game::demo(0),
+            rules: RuleSet::default(),
+            autosave_time: 0.0,
+            events,
+            stats: StatsCollector::default(),
             hud_node: None,
         }
     }
@@ -66,17 +100,45 @@ impl Main {
             .with_param_default("planets_count", 15.to_variant())
             .with_param_default("difficulty", 2.to_variant())
             .with_param_default("demo", true.to_variant())
+            .with_param_default("seed", 0.to_variant())
+            .with_param_default("ai_difficulties", VariantArray::new_shared().to_variant())
+            .with_param_default("layout", 0.to_variant())
             .done();
     }
 
     #[method]
     fn _ready(&mut self, #[base] owner: &Node) {
+        self.rules = match RuleSet::load(&globalize_path(&self.rules_path)) {
+            Ok(rules) => rules,
+            Err(RuleSetError::Io(_)) => RuleSet::default(),
+            Err(err) => {
+                godot_print!("ERROR: invalid rules {}: {:?}", self.rules_path, err);
+                RuleSet::default()
+            }
+        };
+
+        let ai_workers = if self.ai_workers > 0 {
+            self.ai_workers as usize
+        } else {
+            AiWorkerPool::default_workers_count()
+        };
+        let ai_budget = Duration::from_millis(self.ai_budget_ms.max(1) as u64);
+        self.game_state
+            .borrow_mut()
+            .start_ai_workers(ai_workers, ai_budget);
+        if !self.game_state.borrow().has_ai_workers() {
+            godot_print!("WARNING: cannot start AI workers, searching on the main thread");
+        }
+
         self._on_main_start_game(
             owner,
             self.game.get_ais_count(),
             self.game.get_planets_count(),
             self.game.get_difficulty(),
             self.game.is_demo(),
+            0,
+            vec![],
+            0,
         );
 
         let hud_node: Ref<Node2D, _> = instance_scene(&self.hud);
@@ -90,33 +152,21 @@ impl Main {
     pub fn _process(&mut self, #[base] owner: &Node, delta: f64) {
         let start_time = SystemTime::now();
 
-        self.perform_update_time(delta);
+        for _ in 0..self.perform_update_time(delta) {
+            self.perform_tick();
+        }
 
-        self.perform_update_ai();
+        self.perform_draw_fleets();
 
-        let (winner, losers) = self.perform_check_game_over();
-        if self.game.is_demo() && winner.is_some() {
-            self._on_main_start_game(
-                owner,
-                self.game.get_ais_count(),
-                self.game.get_planets_count(),
-                self.game.get_difficulty(),
-                self.game.is_demo(),
-            );
-        } else if !self.game.is_demo() {
-            let game_state = self.game_state.borrow();
-            let current_player = game_state.get_current_player().unwrap();
+        self.perform_autosave(delta);
 
-            if losers
-                .iter()
-                .any(|l| l.properties().id == current_player.properties().id)
-            {
-                HUD::with(&self.hud_node.unwrap(), |hud| hud.game_over(false));
-            } else if let Some(winner) = winner {
-                if winner.properties().id == current_player.properties().id {
-                    HUD::with(&self.hud_node.unwrap(), |hud| hud.game_over(true));
-                }
-            }
+        let events = self.game_state.borrow().poll_events(self.events);
+        for (_, event) in events.iter() {
+            self.stats.record(event);
+        }
+        self.stats.update(&self.game_state.borrow());
+        for (_, event) in events {
+            self.on_game_event(owner, event);
         }
 
         let process_millis = SystemTime::now()
@@ -141,7 +191,161 @@ impl Main {
         planets_count: usize,
         difficulty: usize,
         demo: bool,
+        #[opt] seed: u64,
+        #[opt] ai_difficulties: Vec<usize>,
+        #[opt] layout: usize,
     ) {
+        let seed = if seed == 0 {
+            GameRng::random_seed()
+        } else {
+            seed
+        };
+        let game = if demo {
+            Game::demo(seed)
+        } else {
+            Game::new(ais_count, planets_count, difficulty, seed)
+                .with_ai_difficulties(ai_difficulties)
+        }
+        .with_layout(StarmapLayout::from_index(layout))
+        .with_rules(self.rules);
+        self.start_game(owner, game);
+    }
+
+    #[method]
+    pub fn save_replay(&self, path: String) -> bool {
+        let replay = self.game_state.borrow().get_replay();
+        match replay.map(|replay| replay.save(&globalize_path(&path))) {
+            Some(Ok(())) => true,
+            Some(Err(err)) => {
+                godot_print!("ERROR: cannot save replay {}: {:?}", path, err);
+                false
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    pub fn load_replay(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let replay = match Replay::load(&globalize_path(&path)) {
+            Ok(replay) => replay,
+            Err(err) => {
+                godot_print!("ERROR: cannot load replay {}: {:?}", path, err);
+                return false;
+            }
+        };
+        self.start_game(owner, Game::from_setup(replay.setup.clone()));
+        self.game_state
+            .borrow_mut()
+            .set_replayer(Replayer::new(replay));
+        true
+    }
+
+    #[method]
+    pub fn save_game(&self, path: String) -> bool {
+        let snapshot = self.game_state.borrow().snapshot(self.game.get_setup());
+        match snapshot.save(&globalize_path(&path)) {
+            Ok(()) => true,
+            Err(err) => {
+                godot_print!("ERROR: cannot save game {}: {:?}", path, err);
+                false
+            }
+        }
+    }
+
+    #[method]
+    pub fn load_game(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let snapshot = match GameSnapshot::load(&globalize_path(&path)) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                godot_print!("ERROR: cannot load game {}: {:?}", path, err);
+                return false;
+            }
+        };
+        self.game = Game::from_setup(snapshot.setup.clone());
+        self.autosave_time = 0.0;
+        self.game.restore(self.game_state.clone(), &snapshot, || {
+            self.create_planet(owner)
+        });
+        self.stats.start(&self.game_state.borrow());
+        true
+    }
+
+    #[method]
+    pub fn autosave(&self) -> bool {
+        if self.game.is_demo() {
+            return false;
+        }
+        self.save_game(Self::AUTOSAVE_PATH.to_string())
+    }
+
+    #[method]
+    pub fn load_autosave(&mut self, #[base] owner: &Node) -> bool {
+        self.load_game(owner, Self::AUTOSAVE_PATH.to_string())
+    }
+
+    #[method]
+    pub fn set_paused(&self, paused: bool) {
+        self.game_state
+            .borrow_mut()
+            .get_clock_mut()
+            .set_paused(paused);
+    }
+
+    #[method]
+    pub fn is_paused(&self) -> bool {
+        self.game_state.borrow().get_clock().is_paused()
+    }
+
+    #[method]
+    pub fn set_game_speed(&self, speed: f64) {
+        self.game_state
+            .borrow_mut()
+            .get_clock_mut()
+            .set_speed(speed);
+    }
+
+    #[method]
+    pub fn get_game_speed(&self) -> f64 {
+        self.game_state.borrow().get_clock().get_speed()
+    }
+
+    #[method]
+    fn _unhandled_input(&self, #[base] _owner: &Node, event: Ref<InputEvent>) {
+        let input_event_key: Option<Ref<InputEventKey>> = event.cast();
+        if let Some(event) = input_event_key {
+            let event = unsafe { event.assume_safe() };
+            if !event.is_pressed() || event.is_echo() {
+                return;
+            }
+            let mut game_state = self.game_state.borrow_mut();
+            let clock = game_state.get_clock_mut();
+            match event.scancode() {
+                GlobalConstants::KEY_SPACE | GlobalConstants::KEY_P => {
+                    clock.set_paused(!clock.is_paused())
+                }
+                GlobalConstants::KEY_PLUS
+                | GlobalConstants::KEY_EQUAL
+                | GlobalConstants::KEY_KP_ADD => clock.speed_up(),
+                GlobalConstants::KEY_MINUS | GlobalConstants::KEY_KP_SUBTRACT => clock.slow_down(),
+                _ => (),
+            }
+        }
+    }
+
+    #[method]
+    fn _notification(&self, #[base] _owner: &Node, what: i64) {
+        if what == MainLoop::NOTIFICATION_WM_QUIT_REQUEST {
+            self.autosave();
+        }
+    }
+
+    fn start_game(&mut self, owner: &Node, game: Game) {
+        self.game = game;
+        self.autosave_time = 0.0;
+        self.game
+            .start(self.game_state.clone(), || self.create_planet(owner));
+        self.stats.start(&self.game_state.borrow());
+
         let background = unsafe {
             owner
                 .get_node_as::<AnimatedSprite>("Background")
@@ -151,51 +355,180 @@ impl Main {
         let bg_count = unsafe { bg_count.assume_safe() }
             .as_ref()
             .get_frame_count("default");
+        let game_state = self.game_state.borrow();
         let mut bg_index = background.get_index();
         while bg_index == background.get_index() {
-            bg_index = rand::thread_rng().gen_range(-1..bg_count);
+            bg_index = game_state
+                .get_rng()
+                .with(RngStream::Background, |rng| rng.gen_range(-1..bg_count));
         }
         background.set_frame(bg_index);
+    }
 
-        self.game = if demo {
-            Game::demo()
-        } else {
-            Game::new(ais_count, planets_count, difficulty)
-        };
-        let planet_create_fn = || {
-            let planet_node: Ref<Node2D, _> = instance_scene(&self.planet);
-            let planet_node = unsafe { planet_node.into_shared().assume_safe() };
-            owner.add_child(planet_node, false);
-            planet_node.claim()
-        };
-        self.game.start(self.game_state.clone(), planet_create_fn);
+    fn create_planet(&self, owner: &Node) -> RefPlanetNode2D {
+        let planet_node: Ref<Node2D, _> = instance_scene(&self.planet);
+        let planet_node = unsafe { planet_node.into_shared().assume_safe() };
+        owner.add_child(planet_node, false);
+        planet_node.claim()
+    }
+
+    fn perform_autosave(&mut self, delta: f64) {
+        self.autosave_time += delta;
+        if self.autosave_time >= Self::AUTOSAVE_INTERVAL {
+            self.autosave_time = 0.0;
+            self.autosave();
+        }
     }
 
-    fn perform_update_time(&self, delta: f64) {
+    fn perform_update_time(&self, delta: f64) -> u64 {
         let mut game_state = self.game_state.borrow_mut();
-        game_state.add_time_delta(delta);
+        game_state.add_time_delta(delta)
+    }
+
+    fn perform_tick(&self) {
+        self.game_state.borrow_mut().next_tick();
+        self.perform_update_ai();
+        self.perform_update_fleets();
+        self.perform_update_planets();
+        self.game_state.borrow().update_standings();
     }
 
     fn perform_update_ai(&self) {
-        let mut game_state = self.game_state.borrow_mut();
-        game_state
-            .update_ai()
-            .iter()
-            .for_each(|(ai_player, ai_move)| {
-                let planets = game_state.get_starmap().get_planets();
-                let player = game_state
-                    .get_players()
-                    .iter()
-                    .find(|p| p.properties().id == ai_player.id)
-                    .unwrap();
-                Game::perform_action(&planets, player, *ai_move);
-            });
-    }
-
-    fn perform_check_game_over(&self) -> (Option<Rc<Player2D>>, Vec<Rc<Player2D>>) {
+        let moves: Vec<(usize, PlayerAction)> = if self.game_state.borrow().is_replaying() {
+            self.game_state.borrow_mut().update_replay()
+        } else {
+            self.game_state
+                .borrow_mut()
+                .update_ai()
+                .iter()
+                .map(|(ai_player, ai_move)| (ai_player.id, *ai_move))
+                .collect()
+        };
+        let game_state = self.game_state.borrow();
+        moves.iter().for_each(|(player_id, player_move)| {
+            let player = game_state
+                .get_players()
+                .iter()
+                .find(|p| p.properties().id == *player_id)
+                .unwrap();
+            // Rejected actions are reported by a GameEvent::ActionRejected.
+            let _ = Game::perform_action(&game_state, player, *player_move);
+        });
+    }
+
+    fn perform_update_fleets(&self) {
+        let game_state = self.game_state.borrow();
+        let planets = game_state.get_starmap().get_planets();
+        for fleet in game_state.update_fleets() {
+            if let Some(player) = game_state
+                .get_players()
+                .iter()
+                .find(|p| p.properties().id == fleet.contender_id)
+            {
+                Planet::with(&planets[fleet.destination_id], |planet| {
+                    planet.land_fleet(player, &fleet)
+                });
+            }
+        }
+    }
+
+    fn perform_update_planets(&self) {
         let game_state = self.game_state.borrow();
-        game_state.check_game_over()
+        let planets = game_state.get_starmap().get_planets();
+        let is_extraction_tick = game_state.is_extraction_tick();
+        let tick_duration = game_state.get_clock().get_tick_duration();
+        planets.iter().for_each(|planet_node| {
+            Planet::with(planet_node, |planet| {
+                planet.battle();
+                if is_extraction_tick {
+                    planet.extract();
+                }
+                planet.rotate_orbiters(tick_duration);
+            })
+        });
     }
+
+    fn perform_draw_fleets(&self) {
+        let game_state = self.game_state.borrow();
+        let planets = game_state.get_starmap().get_planets();
+        let clock = game_state.get_clock();
+        let time = game_state.get_time() + clock.get_alpha() * clock.get_tick_duration();
+        for fleet in game_state.get_fleets() {
+            let player = match game_state
+                .get_players()
+                .iter()
+                .find(|p| p.properties().id == fleet.contender_id)
+            {
+                Some(player) => player,
+                None => continue,
+            };
+            let origin_obj = unsafe { planets[fleet.origin_id].assume_safe() };
+            let destination_obj = unsafe { planets[fleet.destination_id].assume_safe() };
+            let origin = origin_obj.global_position();
+            let destination = destination_obj.global_position();
+            let direction = (destination - origin).normalized();
+            let spread = Vector2::new(-direction.y, direction.x);
+            let center = origin + direction * fleet.distance * fleet.progress(time);
+            let half_count = fleet.ships.len() as f32 / 2.0;
+            for (index, ship_props) in fleet.ships.iter().enumerate() {
+                if let Some(ship_node) = player.get_ship(ship_props.id) {
+                    let ship_node_obj: &RigidBody2D = unsafe { ship_node.assume_safe() }.as_ref();
+                    let position = center + spread * 4.0 * (index as f32 - half_count);
+                    Ship::with(&ship_node, |ship| {
+                        ship.fly(ship_node_obj, position, position + direction)
+                    });
+                }
+            }
+        }
+    }
+
+    fn on_game_event(&mut self, owner: &Node, event: GameEvent) {
+        if self.game.is_demo() {
+            if let GameEvent::GameWon(_) = event {
+                self._on_main_start_game(
+                    owner,
+                    self.game.get_ais_count(),
+                    self.game.get_planets_count(),
+                    self.game.get_difficulty(),
+                    self.game.is_demo(),
+                    0,
+                    vec![],
+                    0,
+                );
+            }
+            return;
+        }
+        let current_player_id = match self.game_state.borrow().get_current_player() {
+            Some(current_player) => current_player.properties().id,
+            None => return,
+        };
+        let win = match event {
+            GameEvent::ActionRejected(player_id, err) if player_id == current_player_id => {
+                HUD::with(&self.hud_node.unwrap(), |hud| hud.show_action_error(err));
+                return;
+            }
+            GameEvent::PlayerEliminated(player_id) if player_id == current_player_id => false,
+            GameEvent::GameWon(player_id) if player_id == current_player_id => true,
+            _ => return,
+        };
+        let stats = self.stats.finish(&self.game_state.borrow());
+        if let Err(err) = stats.save(&globalize_path(Self::STATS_PATH)) {
+            godot_print!("ERROR: cannot save stats {}: {:?}", Self::STATS_PATH, err);
+        }
+        HUD::with(&self.hud_node.unwrap(), |hud| hud.game_over(win, &stats));
+    }
+}
+
+impl From<crate::local::model::Color> for gdnative::core_types::Color {
+    fn from(color: crate::local::model::Color) -> Self {
+        gdnative::core_types::Color::from_rgba(color.r, color.g, color.b, color.a)
+    }
+}
+
+fn globalize_path(path: &str) -> String {
+    ProjectSettings::godot_singleton()
+        .globalize_path(path)
+        .to_string()
 }
 
 pub fn instance_scene<Root>(scene: &Ref<PackedScene, Shared>) -> Ref<Root, Unique>
@@ -6,8 +6,14 @@ use rand::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::local::battle::BattleState;
+use crate::local::event::GameEvent;
+use crate::local::fleet::Fleet;
 use crate::local::planet::PlanetBusiness;
 use crate::local::player::*;
+use crate::local::rng::RngStream;
+use crate::local::snapshot::PlanetSnapshot;
+use crate::local::starmap::layout::StarmapLayout;
 use crate::renderer::godot2d::ship::{RefShipNode2D, Ship};
 
 use super::input::InputHandler2D;
@@ -45,18 +51,14 @@ impl Celestial for Planet {
 #[methods]
 impl Planet {
     fn new(owner: &Node2D) -> Self {
-        let mut rng = rand::thread_rng();
         let owner = unsafe { owner.assume_unique() }.cast::<Node2D>().unwrap();
-        let resources_initial =
-            rng.gen_range((Consts::PLANET_RESOURCES_INIT * 0.1)..Consts::PLANET_RESOURCES_INIT);
 
         let properties = CelestialProperties {
             id: 0,
             contender_id: usize::MAX,
             radius: 0.0,
-            resources: resources_initial,
-            resources_increase: resources_initial
-                * rng.gen_range((Consts::PLANET_RESOURCES_INC * 0.1)..Consts::PLANET_RESOURCES_INC),
+            resources: 0.0,
+            resources_increase: 0.0,
             extracted: 0.0,
         };
         Planet {
@@ -66,7 +68,7 @@ impl Planet {
             input_handler_fn: None,
             input_handler: None,
             game_state: None,
-            business: PlanetBusiness::new(),
+            business: PlanetBusiness::default(),
         }
     }
 
@@ -81,34 +83,20 @@ impl Planet {
         event: Ref<InputEvent>,
         _shape_idx: isize,
     ) {
-        let target = Box::new(self);
-        let player_action = self
-            .input_handler
-            .as_ref()
-            .unwrap()
-            .borrow_mut()
-            .convert(self.properties(), event);
-        self.input_handler_fn.as_ref().unwrap()(target, player_action);
-    }
-
-    #[method]
-    pub fn _on_ship_arrival(&self, #[base] owner: &Node2D, ship_node: RefShipNode2D) {
-        let props = self.properties();
-        let ship_node_obj: &RigidBody2D = unsafe { ship_node.assume_safe() }.as_ref();
-        if ship_node_obj.linear_velocity().length() == 0.0
-            || ship_node_obj.get_angle_to(owner.global_position()).abs() > 0.004
-        {
-            return;
+        if let Some(event) = InputHandler2D::convert_event(event) {
+            let target = Box::new(self);
+            let player_action = self
+                .input_handler
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .convert(self.properties(), event);
+            self.input_handler_fn.as_ref().unwrap()(target, player_action);
         }
-        ship_node_obj.set_linear_velocity(Vector2::new(0.0, 0.0));
-
-        Ship::with_mut(&ship_node, |ship| {
-            ship.orbit(ship_node_obj, props.id, self.owner, props.radius);
-        });
     }
 
-    #[method]
-    pub fn _on_resource_timer_timeout(&self, #[base] owner: &Node2D) {
+    pub fn extract(&self) {
+        let owner = unsafe { self.owner.assume_safe() };
         let mut props = self.properties.borrow_mut();
         let planet_orbiters = unsafe {
             owner
@@ -138,25 +126,28 @@ impl Planet {
         planet_label.set_text(label);
     }
 
-    #[method]
-    pub fn _on_orbiters_timer_timeout(&self, #[base] owner: &Node2D) {
+    pub fn rotate_orbiters(&self, tick_duration: f64) {
         let planet_orbiters = unsafe {
-            owner
+            self.owner
+                .assume_safe()
                 .get_node_as::<Node2D>("Orbiters")
                 .expect("Cannot resolve Orbiters")
                 .assume_unique()
         };
         let rotation = planet_orbiters.global_rotation();
-        planet_orbiters.set_global_rotation(rotation - 0.001 * Consts::MOVE_SHIP_SPEED_MULT as f64);
+        let speed_mult = self.get_game_state().get_rules().move_ship_speed_mult;
+        planet_orbiters.set_global_rotation(rotation - 0.01 * speed_mult as f64 * tick_duration);
     }
 
-    #[method]
-    pub fn _process(&self, #[base] owner: &Node2D, _delta: f64) {
+    pub fn battle(&self) {
         let game_state = self.get_game_state();
         let players = game_state.get_players();
 
         let ships_by_player_on_planet = game_state.get_ships_by_player_on_planet(self.properties());
-        let (winner, casualties) = self.business.battle(ships_by_player_on_planet);
+        let result = game_state.get_rng().with(RngStream::Battle, |rng| {
+            self.business.battle(ships_by_player_on_planet, rng)
+        });
+        let casualties = result.get_casualties();
 
         if !casualties.is_empty() {
             let kill_ship = unsafe {
@@ -174,7 +165,7 @@ impl Planet {
                 .find(|player| player.properties().id == casualty.contender_id)
             {
                 let mut casualty_player_ships = casualty_player.ships.borrow_mut();
-                let (index, casualty) = casualty_player_ships
+                let (index, casualty_node) = casualty_player_ships
                     .iter_mut()
                     .enumerate()
                     .find(|(_, ship_node)| {
@@ -184,37 +175,44 @@ impl Planet {
                         })
                     })
                     .unwrap();
-                unsafe { casualty.assume_safe() }.queue_free();
+                unsafe { casualty_node.assume_safe() }.queue_free();
                 casualty_player_ships.remove(index);
+                game_state.emit_event(GameEvent::ShipDestroyed(casualty));
             };
         }
 
-        if let Some(winner) = winner {
+        if let Some(winner) = result.winner {
+            let props = self.properties();
+            if winner.id == props.contender_id {
+                return;
+            }
+            if let Some(loser) = players
+                .iter()
+                .find(|player| player.properties().id == props.contender_id)
+            {
+                loser.planets.borrow_mut().retain(|planet| {
+                    Planet::with(planet, |planet| planet.properties().id != props.id)
+                });
+            }
             let winner = players
                 .iter()
                 .find(|player| player.properties().id == winner.id)
                 .unwrap();
             let winner_props = winner.properties();
-            let winner_planet = unsafe { owner.assume_shared() };
             self.properties.borrow_mut().contender_id = winner_props.id;
-            winner.planets.borrow_mut().push(winner_planet);
-            if let Some(loser) = players.iter().find(|player| {
-                player.properties().id == self.properties().contender_id
-                    && player.properties().id != winner_props.id
-            }) {
-                loser.planets.borrow_mut().retain(|planet| {
-                    Planet::with(planet, |planet| {
-                        planet.properties().id != self.properties().id
-                    })
-                });
-            }
+            winner.planets.borrow_mut().push(self.owner);
+            game_state.emit_event(GameEvent::PlanetCaptured(
+                props.id,
+                props.contender_id,
+                winner_props.id,
+            ));
             let planet_sprite = unsafe {
                 self.owner
                     .assume_safe()
                     .get_node_as::<Sprite>("Area2D/Sprite")
             }
             .expect("Cannot resolve Area2D/Sprite");
-            planet_sprite.set_modulate(winner_props.color);
+            planet_sprite.set_modulate(winner_props.color.into());
         }
     }
 
@@ -242,12 +240,21 @@ impl Planet {
             let ship_node = ship_node.into_shared();
             let ship_node_obj: &RigidBody2D = unsafe { ship_node.assume_safe() }.as_ref();
             player.add_ship(ship_node);
-            let ships_count = player.ships.borrow().len();
 
+            let game_state = self.get_game_state();
+            let ship_id = game_state.next_vessel_id();
+            let angle = game_state
+                .get_rng()
+                .with(RngStream::Orbit, Ship::orbit_angle);
             Ship::with_mut(&ship_node, |ship| {
-                ship.set_id(player.properties(), ships_count);
-                ship.orbit(ship_node_obj, props.id, self.owner, props.radius);
+                ship.set_id(player.properties(), ship_id);
+                ship.orbit(ship_node_obj, props.id, self.owner, props.radius, angle);
             });
+            game_state.emit_event(GameEvent::ShipBuilt(VesselProperties {
+                id: ship_id,
+                contender_id: player.properties().id,
+                celestial_id: props.id,
+            }));
         }
     }
 
@@ -260,24 +267,27 @@ impl Planet {
         let mut game_state = self.game_state.as_ref().unwrap().borrow_mut();
         props.contender_id = game_state.get_players().len();
         let player = Player2D::new(props.contender_id, self.owner, ship_node, is_bot);
-        let ships_count = player.ships.borrow().len();
+        let ship_id = game_state.next_vessel_id();
         let planet_sprite = unsafe {
             self.owner
                 .assume_safe()
                 .get_node_as::<Sprite>("Area2D/Sprite")
                 .expect("Cannot resolve Area2D/Sprite")
         };
-        planet_sprite.set_modulate(player.properties().color);
+        planet_sprite.set_modulate(player.properties().color.into());
 
+        let angle = game_state
+            .get_rng()
+            .with(RngStream::Orbit, Ship::orbit_angle);
         Ship::with_mut(&ship_node, |ship| {
-            ship.set_id(player.properties(), ships_count);
-            ship.orbit(ship_node_obj, props.id, self.owner, props.radius);
+            ship.set_id(player.properties(), ship_id);
+            ship.orbit(ship_node_obj, props.id, self.owner, props.radius, angle);
         });
 
         game_state.add_player(player.into());
     }
 
-    pub fn move_ships(&self, percent: usize, player: &Player2D, destination: &RefPlanetNode2D) {
+    pub fn move_ships(&self, size: FleetSize, player: &Player2D, destination: &RefPlanetNode2D) {
         let planet_orbiters = unsafe {
             self.owner
                 .assume_safe()
@@ -299,7 +309,7 @@ impl Planet {
         }
         let count: usize = self
             .business
-            .count_ships_to_move(selected_ships.len(), percent);
+            .count_ships_to_move(selected_ships.len(), size);
         if count > 0 {
             let move_ship = unsafe {
                 self.owner
@@ -320,10 +330,12 @@ impl Planet {
                 .get_parent()
                 .unwrap();
 
+            let destination_id = Planet::with(destination, |planet| planet.properties().id);
+            let destination_obj = unsafe { destination.assume_safe() }.as_ref();
+            let mut ships = Vec::with_capacity(count);
             for ship_node in selected_ships {
                 let ship_node_obj: &RigidBody2D =
                     unsafe { ship_node.assume_safe() }.as_ref().cast().unwrap();
-                Ship::with(&ship_node, |ship| ship.leave_orbit());
 
                 let position = ship_node_obj.global_position();
                 let parent_ref =
@@ -335,20 +347,39 @@ impl Planet {
                 unsafe { root_node.assume_safe() }
                     .as_ref()
                     .add_child(ship_instance, false);
-                ship_node_obj.set_global_position(position);
-
-                let destination_obj = unsafe { destination.assume_safe() }.as_ref();
-                ship_node_obj.look_at(destination_obj.global_position());
-                ship_node_obj.set_linear_velocity(
-                    (destination_obj.global_position() - position).normalized()
-                        * 10.0
-                        * Consts::MOVE_SHIP_SPEED_MULT,
-                );
+
+                ships.push(Ship::with(&ship_node, |ship| {
+                    ship.leave_orbit(ship_node_obj);
+                    ship.fly(ship_node_obj, position, destination_obj.global_position());
+                    ship.properties()
+                }));
             }
+            self.get_game_state().launch_fleet(
+                player.properties().id,
+                self.properties().id,
+                destination_id,
+                ships,
+            );
         }
     }
 
-    pub fn set_random_features(&self) {
+    pub fn land_fleet(&self, player: &Player2D, fleet: &Fleet) {
+        let props = self.properties();
+        for ship_props in fleet.ships.iter() {
+            if let Some(ship_node) = player.get_ship(ship_props.id) {
+                let ship_node_obj: &RigidBody2D = unsafe { ship_node.assume_safe() }.as_ref();
+                let angle = self
+                    .get_game_state()
+                    .get_rng()
+                    .with(RngStream::Orbit, Ship::orbit_angle);
+                Ship::with_mut(&ship_node, |ship| {
+                    ship.orbit(ship_node_obj, props.id, self.owner, props.radius, angle);
+                });
+            }
+        }
+    }
+
+    pub fn set_random_features(&self, layout: StarmapLayout, count: usize) {
         let mut props = self.properties.borrow_mut();
         let owner = unsafe { self.owner.assume_safe() }.as_ref();
 
@@ -356,7 +387,21 @@ impl Planet {
         let viewport_width = viewport_rect.size.x;
         let viewport_height = viewport_rect.size.y;
 
-        let mut rng = rand::thread_rng();
+        let game_state = self.get_game_state();
+        let rules = game_state.get_rules();
+        let (resources_initial, resources_inc, scale, x_ratio, y_ratio) =
+            game_state.get_rng().with(RngStream::Starmap, |rng| {
+                (
+                    rng.gen_range((rules.planet_resources_init * 0.1)..rules.planet_resources_init),
+                    rng.gen_range((rules.planet_resources_inc * 0.1)..rules.planet_resources_inc),
+                    rng.gen_range(0.5..2.5),
+                    rng.gen_range(0.0..1.0),
+                    rng.gen_range(0.0..1.0),
+                )
+            });
+        props.resources = resources_initial;
+        props.resources_increase = resources_initial * resources_inc;
+
         let planet_area = unsafe {
             owner
                 .get_node_as::<Area2D>("Area2D")
@@ -375,19 +420,90 @@ impl Planet {
         }
         .get_size()
         .x * 0.5;
-        let scale = rng.gen_range(0.5..2.5) * planet_sprite.scale().x;
+        let scale = scale * planet_sprite.scale().x;
         let scale_vector = Vector2::new(scale, scale);
         planet_area.set_scale(scale_vector);
 
         props.radius = 0.45 * scale * size;
         let diameter = 2.0 * props.radius;
-        let x_offset =
-            (rng.gen_range(0.0..1.0) * viewport_width).clamp(diameter, viewport_width - diameter);
-        let y_offset =
-            (rng.gen_range(0.0..1.0) * viewport_height).clamp(diameter, viewport_height - diameter);
+        let (x_ratio, y_ratio) = layout.place(props.id, count, x_ratio, y_ratio);
+        let x_offset = (x_ratio * viewport_width).clamp(diameter, viewport_width - diameter);
+        let y_offset = (y_ratio * viewport_height).clamp(diameter, viewport_height - diameter);
         owner.set_position(Vector2::new(x_offset, y_offset));
     }
 
+    pub fn restore_features(&self, snapshot: &PlanetSnapshot) {
+        let owner = unsafe { self.owner.assume_safe() }.as_ref();
+        let planet_area = unsafe {
+            owner
+                .get_node_as::<Area2D>("Area2D")
+                .expect("Cannot resolve Area2D")
+        };
+        let planet_sprite = unsafe {
+            owner
+                .get_node_as::<Sprite>("Area2D/Sprite")
+                .expect("Cannot resolve Area2D/Sprite")
+        };
+        let size = unsafe {
+            planet_sprite
+                .texture()
+                .expect("Unable to get Texture")
+                .assume_safe()
+        }
+        .get_size()
+        .x * 0.5;
+        let scale = snapshot.properties.radius / (0.45 * size);
+        planet_area.set_scale(Vector2::new(scale, scale));
+        if snapshot.properties.contender_id != usize::MAX {
+            planet_sprite.set_modulate(get_color(snapshot.properties.contender_id).into());
+        }
+
+        let (x, y) = snapshot.position;
+        owner.set_position(Vector2::new(x, y));
+        self.properties.replace(snapshot.properties);
+        self.business.set_battle_state(snapshot.battle.clone());
+    }
+
+    pub fn get_battle_state(&self) -> BattleState {
+        self.business.get_battle_state()
+    }
+
+    pub fn restore_ship(
+        &self,
+        player_props: ContenderProperties,
+        ship_props: VesselProperties,
+    ) -> RefShipNode2D {
+        let props = self.properties();
+        let owner = unsafe { self.owner.assume_safe() }.as_ref();
+        let ship_node: Ref<RigidBody2D, _> = instance_scene(&self.ship);
+        let ship_node = ship_node.into_shared();
+        let ship_node_obj: &RigidBody2D = unsafe { ship_node.assume_safe() }.as_ref();
+
+        let is_travelling = ship_props.celestial_id == usize::MAX;
+        if is_travelling {
+            let root_node = owner.get_parent().unwrap();
+            let ship_instance: TInstance<Ship> =
+                unsafe { ship_node.assume_safe() }.cast_instance().unwrap();
+            unsafe { root_node.assume_safe() }
+                .as_ref()
+                .add_child(ship_instance, false);
+            ship_node_obj.set_global_position(owner.global_position());
+        }
+        let angle = self
+            .get_game_state()
+            .get_rng()
+            .with(RngStream::Orbit, Ship::orbit_angle);
+        Ship::with_mut(&ship_node, |ship| {
+            ship.set_id(player_props, ship_props.id);
+            if is_travelling {
+                ship.leave_orbit(ship_node_obj);
+            } else {
+                ship.orbit(ship_node_obj, props.id, self.owner, props.radius, angle);
+            }
+        });
+        ship_node
+    }
+
     pub fn set_id(&self, id: usize) {
         let mut props = self.properties.borrow_mut();
         props.id = id;
@@ -409,6 +525,7 @@ impl Planet {
     }
 
     pub fn set_game_state(&mut self, game_state: Rc<RefCell<GameState<Starmap2D, Player2D>>>) {
+        self.business = PlanetBusiness::new(*game_state.borrow().get_rules());
         self.game_state = Some(game_state);
     }
 
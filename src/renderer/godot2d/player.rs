@@ -1,11 +1,10 @@
-use gdnative::prelude::*;
-
 use std::cell::*;
 
-use super::planet::RefPlanetNode2D;
+use super::planet::{Planet, RefPlanetNode2D};
 use super::ship::{RefShipNode2D, Ship};
 use crate::local::model::*;
 use crate::local::player::*;
+use crate::local::snapshot::PlayerSnapshot;
 
 pub struct Player2D {
     pub planets: RefCell<Vec<RefPlanetNode2D>>,
@@ -68,34 +67,44 @@ impl Player for Player2D {
 
         player_ships_on_planet
     }
+
+    fn snapshot(&self) -> PlayerSnapshot {
+        PlayerSnapshot {
+            properties: self.properties(),
+            planets: self
+                .planets
+                .borrow()
+                .iter()
+                .map(|planet_node| Planet::with(planet_node, |planet| planet.properties().id))
+                .collect(),
+            ships: self
+                .ships
+                .borrow()
+                .iter()
+                .map(|ship_node| Ship::with(ship_node, |ship| ship.properties()))
+                .collect(),
+        }
+    }
 }
 
-fn get_color(id: usize) -> Color {
-    let colors = [
-        (230, 25, 75),   // Red
-        (60, 180, 75),   // Green
-        (255, 225, 25),  // Yellow
-        (0, 130, 200),   // Blue
-        (245, 130, 48),  // Orange
-        (145, 30, 180),  // Purple
-        (70, 240, 240),  // Cyan
-        (240, 50, 230),  // Magenta
-        (210, 245, 60),  // Lime
-        (250, 190, 212), // Pink
-        (0, 128, 128),   // Teal
-        (220, 190, 255), // Lavender
-        (170, 110, 40),  // Brown
-        (255, 250, 200), // Beige
-        (128, 0, 0),     // Maroon
-        (170, 255, 195), // Mint
-        (128, 128, 0),   // Olive
-        (255, 215, 180), // Apricot
-        (0, 0, 128),     // Navy
-        (128, 128, 128), // Grey
-    ];
-    Color::from_rgb(
-        colors[id].0 as f32 / 255.0,
-        colors[id].1 as f32 / 255.0,
-        colors[id].2 as f32 / 255.0,
-    )
+impl Player2D {
+    pub fn restore(
+        properties: ContenderProperties,
+        planets: Vec<RefPlanetNode2D>,
+        ships: Vec<RefShipNode2D>,
+    ) -> Self {
+        Player2D {
+            properties: RefCell::new(properties),
+            planets: RefCell::new(planets),
+            ships: RefCell::new(ships),
+        }
+    }
+
+    pub fn get_ship(&self, id: usize) -> Option<RefShipNode2D> {
+        self.ships
+            .borrow()
+            .iter()
+            .find(|ship_node| Ship::with(ship_node, |ship| ship.properties().id == id))
+            .cloned()
+    }
 }
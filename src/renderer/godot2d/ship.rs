@@ -70,18 +70,21 @@ impl Ship {
         celestial_id: usize,
         planet_node: RefPlanetNode2D,
         radius: f32,
+        angle: f32,
     ) {
         let mut props = self.properties.borrow_mut();
         props.celestial_id = celestial_id;
 
-        let mut rng = rand::thread_rng();
-        let angle = rng.gen_range(0.0..2.0);
         let position = Vector2::new(radius, 0.0).rotated(angle);
         owner.set_rotation(3.0 * FRAC_PI_2 + angle as f64);
         owner.set_position(position);
         unsafe { owner.call_deferred("reparent", &[planet_node.to_variant()]) };
     }
 
+    pub fn orbit_angle<R: Rng>(rng: &mut R) -> f32 {
+        rng.gen_range(0.0..2.0)
+    }
+
     pub fn find_player(&self, players: &[Rc<Player2D>]) -> Option<Rc<Player2D>> {
         let props = self.properties();
         let player = players
@@ -114,12 +117,18 @@ impl Ship {
                 .get_node_as::<Sprite>("Sprite")
                 .expect("Cannot resolve Sprite")
         };
-        ship_sprite.set_modulate(player_props.color);
+        ship_sprite.set_modulate(player_props.color.into());
     }
 
-    pub fn leave_orbit(&self) {
+    pub fn leave_orbit(&self, owner: &RigidBody2D) {
         let mut props = self.properties.borrow_mut();
         props.celestial_id = usize::MAX;
+        owner.set_linear_velocity(Vector2::new(0.0, 0.0));
+    }
+
+    pub fn fly(&self, owner: &RigidBody2D, position: Vector2, destination_position: Vector2) {
+        owner.set_global_position(position);
+        owner.look_at(destination_position);
     }
 
     pub fn with_mut<F, T>(base: &RefShipNode2D, mut with_fn: F) -> T
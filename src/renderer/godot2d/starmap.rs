@@ -2,10 +2,12 @@ use gdnative::prelude::*;
 
 use super::planet::Planet;
 use crate::local::model::*;
+use crate::local::snapshot::PlanetSnapshot;
 use crate::local::starmap::builder::StarmapBuilder;
 use crate::local::starmap::*;
 use crate::renderer::godot2d::planet::RefPlanetNode2D;
 
+#[derive(Default)]
 pub struct Starmap2D {
     planets: Vec<RefPlanetNode2D>,
 }
@@ -33,7 +35,7 @@ impl Starmap for Starmap2D {
         H: Fn(&RefPlanetNode2D),
         Self: Sized,
     {
-        StarmapBuilder::new(count, Starmap2D { planets: vec![] })
+        StarmapBuilder::new(count, Starmap2D::default())
     }
 
     fn destroy(&self) {
@@ -42,6 +44,23 @@ impl Starmap for Starmap2D {
             .for_each(|p| unsafe { p.assume_safe() }.queue_free());
     }
 
+    fn snapshot(&self) -> Vec<PlanetSnapshot> {
+        self.planets
+            .iter()
+            .map(|planet_node| {
+                let position = unsafe { planet_node.assume_safe() }.position();
+                let (properties, battle) = Planet::with(planet_node, |planet| {
+                    (planet.properties(), planet.get_battle_state())
+                });
+                PlanetSnapshot {
+                    properties,
+                    position: (position.x, position.y),
+                    battle,
+                }
+            })
+            .collect()
+    }
+
     fn get_distance_between(planet1: &RefPlanetNode2D, planet2: &RefPlanetNode2D) -> f32 {
         let planet1_obj: &Node2D = unsafe { planet1.assume_safe() }.as_ref().cast().unwrap();
         let planet2_obj: &Node2D = unsafe { planet2.assume_safe() }.as_ref().cast().unwrap();